@@ -0,0 +1,78 @@
+//! Counts the allocations made while growing the algae system, with the
+//! next state sized by a growth hint, by the growth of the last generation,
+//! and grown into from the length of the current state as before.
+//!
+//!     cargo run --release --example growth_bench
+
+extern crate lsystem;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use lsystem::{LRules, LSystem, MapRules};
+
+/// The system allocator, counting every allocation and reallocation.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const GENERATIONS: usize = 30;
+
+fn measure<F>(name: &str, mut step: F) where F: FnMut() {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        step();
+    }
+    let elapsed = start.elapsed();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{:>18} {:>6} allocations {:>12?}", name, count, elapsed);
+}
+
+fn main() {
+    let rules = MapRules::from_str_rules(&[('A', "AB"), ('B', "A")]);
+
+    // each generation of the algae system is about the golden ratio times
+    // as long as the one before
+    let mut hinted = LSystem::new(rules.clone(), vec!['A']);
+    hinted.set_growth_hint(1.618_034);
+    measure("growth hint", || {
+        hinted.advance();
+    });
+
+    let mut estimated = LSystem::new(rules.clone(), vec!['A']);
+    measure("estimated", || {
+        estimated.advance();
+    });
+
+    // what advance() did before: start from the current length and grow
+    let mut state = vec!['A'];
+    measure("current length", || {
+        let mut next = Vec::with_capacity(state.len());
+        rules.map_all_into(&state, &mut next);
+        state = next;
+    });
+
+    assert_eq!(hinted.current_state(), estimated.current_state());
+    assert_eq!(hinted.current_state(), &state[..]);
+}
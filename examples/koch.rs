@@ -0,0 +1,38 @@
+//! Draws the Koch snowflake and writes it out as an SVG.
+//!
+//!     cargo run --example koch [generations] [path]
+//!
+//! The snowflake turns by exactly 60 degrees, so every line is drawn in one
+//! of six headings and the outline closes up on itself.
+
+extern crate lsystem;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+use lsystem::turtle::{interpret_polylines, polylines_to_svg, SvgOptions, TurtleConfig};
+use lsystem::char_system;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let generations: usize = args.next().map_or(4, |s| s.parse().expect("generations must be a number"));
+    let path = args.next().unwrap_or_else(|| "koch.svg".to_string());
+
+    let system = char_system("F--F--F", &[('F', "F+F--F+F")]);
+    let state = system.generations().nth(generations).unwrap();
+
+    let config = TurtleConfig::new(1.0, 60f64.to_radians());
+    let polylines = interpret_polylines(&state, &config);
+    let end = *polylines.last().unwrap().points.last().unwrap();
+    println!("{} lines, ending at ({:e}, {:e})", state.iter().filter(|&&c| c == 'F').count(), end.0, end.1);
+
+    let opts = SvgOptions {
+        margin: 2.0,
+        stroke_width: 0.5,
+        ..SvgOptions::default()
+    };
+    let mut file = File::create(&path).expect("could not create the output file");
+    file.write_all(polylines_to_svg(&polylines, &opts).as_bytes()).expect("could not write the output file");
+    println!("wrote {}", path);
+}
@@ -0,0 +1,34 @@
+//! Compares `next()` with `next_parallel()` on the Pythagoras tree.
+//!
+//!     cargo run --release --features rayon --example parallel_bench
+
+extern crate lsystem;
+
+use std::time::Instant;
+
+use lsystem::{LSystem, MapRules};
+
+fn system() -> LSystem<char, MapRules<char>> {
+    let mut rules = MapRules::new();
+    rules.set_str('1', "11");
+    rules.set_str('0', "1[0]0");
+    LSystem::new(rules, vec!['0'])
+}
+
+fn main() {
+    let mut sequential = system();
+    let mut parallel = system();
+    println!("{:>4} {:>10} {:>12} {:>12}", "gen", "atoms", "next", "parallel");
+    for generation in 1..21 {
+        let start = Instant::now();
+        let a = sequential.next().unwrap();
+        let seq_time = start.elapsed();
+
+        let start = Instant::now();
+        let b = parallel.next_parallel().unwrap();
+        let par_time = start.elapsed();
+
+        assert_eq!(a, b);
+        println!("{:>4} {:>10} {:>12?} {:>12?}", generation, a.len(), seq_time, par_time);
+    }
+}
@@ -0,0 +1,34 @@
+//! Draws the Sierpinski arrowhead curve and writes it out as a PNG.
+//!
+//!     cargo run --features image --example sierpinski_png [generations] [path]
+//!
+//! Both `F` and `G` draw a line, and they are the edges being rewritten, so
+//! at every even generation the curve fills out a Sierpinski triangle.
+
+extern crate lsystem;
+
+use std::env;
+
+use lsystem::turtle::{interpret, rasterize, TurtleConfig};
+use lsystem::char_system;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let generations: usize = args.next().map_or(8, |s| s.parse().expect("generations must be a number"));
+    let path = args.next().unwrap_or_else(|| "sierpinski.png".to_string());
+
+    let system = char_system("F", &[('F', "G-F-G"), ('G', "F+G+F")]);
+    let state = system.generations().nth(generations).unwrap();
+
+    // start heading along the x axis, so that the triangle sits on its base
+    let config = TurtleConfig {
+        initial_heading: 0.0,
+        ..TurtleConfig::new(1.0, 60f64.to_radians())
+    };
+    let segments = interpret(&state, &config);
+    println!("{} lines", segments.len());
+
+    let image = rasterize(&segments, 1024, 1024);
+    image.save(&path).expect("could not write the output file");
+    println!("wrote {}", path);
+}
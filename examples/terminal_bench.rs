@@ -0,0 +1,54 @@
+//! Compares rule lookups on a large alphabet where only a few atoms expand:
+//! `MapRules`, `MapRules` behind a set of declared terminals, and
+//! `ScanRules`.
+//!
+//!     cargo run --release --example terminal_bench
+
+extern crate lsystem;
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use lsystem::{LRules, MapRules, ScanRules};
+
+/// `MapRules` that first checks whether an atom is a known terminal.
+struct DeclaredTerminals {
+    rules: MapRules<char>,
+    terminals: HashSet<char>,
+}
+
+impl LRules<char> for DeclaredTerminals {
+    fn map(&self, input: &char) -> Option<Vec<char>> {
+        if self.terminals.contains(input) {
+            None
+        } else {
+            self.rules.map(input)
+        }
+    }
+}
+
+fn time<P: LRules<char>>(name: &str, rules: &P, state: &[char]) {
+    let start = Instant::now();
+    let mut expanded = 0;
+    for _ in 0..20 {
+        expanded += state.iter().filter(|atom| rules.map(atom).is_some()).count();
+    }
+    println!("{:>18} {:>12?} ({} expanded)", name, start.elapsed(), expanded);
+}
+
+fn main() {
+    let rules = MapRules::from_str_rules(&[('A', "AB"), ('F', "FF"), ('X', "F[+X]")]);
+    let alphabet: Vec<char> = (b'A'..b'Z' + 1).map(char::from).collect();
+    let state: Vec<char> = (0..1_000_000).map(|i| alphabet[(i * 7919) % alphabet.len()]).collect();
+
+    let terminals = alphabet.iter().cloned().filter(|atom| !rules.contains(atom)).collect();
+    let declared = DeclaredTerminals {
+        rules: rules.clone(),
+        terminals,
+    };
+    let scan = ScanRules::from(rules.clone());
+
+    time("MapRules", &rules, &state);
+    time("declared terminals", &declared, &state);
+    time("ScanRules", &scan, &state);
+}
@@ -3,14 +3,10 @@ extern crate lsystem;
 use lsystem::*;
 
 fn main() {
-    let mut rules: MapRules<char> = MapRules::new();
-    rules.set_str('A', "AB");
-    rules.set_str('B', "A");
-    let axiom = "A".chars().collect();
-    println!("{}", show(&axiom));
-    let mut system = LSystem::new(rules, axiom);
-    for i in 1..10 {
-        let out = system.next().unwrap();
-        println!("{:3} ({:5})-> {}", i, out.len(), show(&out));
+    let system = char_system("A", &[('A', "AB"), ('B', "A")]);
+    let generations = run(system, 9);
+    println!("{}", show(&generations[0]));
+    for (i, out) in generations.iter().enumerate().skip(1) {
+        println!("{:3} ({:5})-> {}", i, out.len(), show(out));
     }
 }
@@ -0,0 +1,231 @@
+//! Building a generation a slice at a time, for callers that cannot block
+//! while a whole generation is expanded.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::mem;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use {LRules, LSystem};
+
+/// How many atoms `next_within` expands between looks at the clock.
+#[cfg(feature = "std")]
+const ATOMS_PER_CLOCK_CHECK: usize = 4096;
+
+/// How far `LSystem::next_budgeted` has got with the next generation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress<T> {
+    /// some atoms of the current state are still to be expanded
+    InProgress,
+    /// the generation is complete, and this is its state
+    Done(Vec<T>),
+    /// a full pass found no atom to expand, so the L-System has terminated
+    Terminated,
+}
+
+/// A generation part way through being built by `next_budgeted`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Budgeted<T> {
+    /// the expansion of the atoms consumed so far
+    next: Vec<T>,
+    /// the number of atoms of the current state consumed so far
+    consumed: usize,
+    /// whether any of them were expanded
+    expanded: bool,
+}
+
+impl<T> Default for Budgeted<T> {
+    fn default() -> Budgeted<T> {
+        Budgeted {
+            next: Vec::new(),
+            consumed: 0,
+            expanded: false,
+        }
+    }
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Clone {
+    /// work towards the next generation, expanding at most
+    /// `max_atoms_processed` atoms of the current state, and continuing from
+    /// where the previous call left off.  This spreads an expensive
+    /// generation over several calls, such as one per frame of a user
+    /// interface.
+    ///
+    /// Every atom consumed counts against the budget, whether it has a rule
+    /// or not.  The current state is left untouched until the generation is
+    /// complete, so `current_state()` shows the last whole generation, and
+    /// context sensitive rules see it just as they would in `next()`.  Once
+    /// every atom has been consumed this returns `Done` with the new state,
+    /// or `Terminated` if none of them had a rule, and the following call
+    /// starts on the generation after.
+    ///
+    /// Any other way of moving the L-System on, such as `next()`,
+    /// `next_partial()` or `reset()`, abandons a generation that
+    /// `next_budgeted` was part way through.
+    ///
+    /// ```
+    /// use lsystem::{LSystem, MapRules, Progress};
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let mut system = LSystem::new(rules, vec!['A', 'B', 'A']);
+    ///
+    /// assert_eq!(Progress::InProgress, system.next_budgeted(2));
+    /// assert_eq!(&['A', 'B', 'A'], system.current_state());
+    /// assert_eq!(Progress::Done(vec!['A', 'B', 'A', 'A', 'B']), system.next_budgeted(2));
+    /// assert_eq!(1, system.generation());
+    /// ```
+    pub fn next_budgeted(&mut self, max_atoms_processed: usize) -> Progress<T> {
+        let start = self.budgeted.consumed;
+        if start == 0 {
+            self.start_generation();
+            let capacity = self.next_capacity();
+            self.budgeted.next.reserve(capacity);
+        }
+        let end = self.state.len().min(start.saturating_add(max_atoms_processed));
+        for i in start..end {
+            match self.rules.map_at(&self.state, i) {
+                Some(atoms) => {
+                    self.observer.rewrote(&self.state[i], &atoms);
+                    self.budgeted.next.extend(atoms);
+                    self.budgeted.expanded = true;
+                },
+                None => self.budgeted.next.push(self.state[i].clone()),
+            }
+        }
+        self.budgeted.consumed = end;
+        if end < self.state.len() {
+            return Progress::InProgress;
+        }
+
+        let budgeted = mem::take(&mut self.budgeted);
+        if !budgeted.expanded {
+            return Progress::Terminated;
+        }
+        self.previous_len = Some(self.state.len());
+        self.state = budgeted.next;
+        self.generation += 1;
+        self.cursor = 0;
+        self.pass_rewritten = false;
+        Progress::Done(self.state.clone())
+    }
+
+    /// work towards the next generation like `next_budgeted`, for as long
+    /// as `budget` allows rather than for a number of atoms, and continuing
+    /// from where the previous call left off.  This suits a tool that must
+    /// stay responsive, giving each frame a fixed slice of time.
+    ///
+    /// Reading the clock is not free, so atoms are expanded in batches of a
+    /// few thousand, and the time is checked after each batch.  A call can
+    /// therefore run over its budget by up to one batch, and always expands
+    /// at least one batch, so even a zero budget makes progress.  The results
+    /// are the same as for `next_budgeted`, and the two can be mixed.
+    ///
+    /// This requires the `std` feature, and a platform with a clock; on
+    /// `wasm32-unknown-unknown`, `Instant` is not available.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use lsystem::{LSystem, MapRules, Progress};
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let mut system = LSystem::new(rules, vec!['A']);
+    ///
+    /// let state = loop {
+    ///     match system.next_within(Duration::from_millis(16)) {
+    ///         Progress::InProgress => continue,
+    ///         Progress::Done(state) => break state,
+    ///         Progress::Terminated => unreachable!(),
+    ///     }
+    /// };
+    /// assert_eq!(vec!['A', 'B'], state);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn next_within(&mut self, budget: Duration) -> Progress<T> {
+        let start = Instant::now();
+        loop {
+            match self.next_budgeted(ATOMS_PER_CLOCK_CHECK) {
+                Progress::InProgress if start.elapsed() < budget => continue,
+                progress => return progress,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MapRules;
+
+    fn algae() -> LSystem<char, MapRules<char>> {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AB");
+        rules.set_str('B', "A");
+        LSystem::new(rules, vec!['A'])
+    }
+
+    #[test]
+    fn test_matches_next() {
+        let mut budgeted = algae();
+        for (expected, budget) in algae().take(10).zip([1, 3, 7].iter().cycle()) {
+            let state = loop {
+                match budgeted.next_budgeted(*budget) {
+                    Progress::InProgress => continue,
+                    Progress::Done(state) => break state,
+                    Progress::Terminated => panic!("algae never terminates"),
+                }
+            };
+            assert_eq!(expected, state);
+        }
+        assert_eq!(10, budgeted.generation());
+    }
+
+    #[test]
+    fn test_terminates() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "B");
+        let mut system = LSystem::new(rules, vec!['A', 'C']);
+        assert_eq!(Progress::InProgress, system.next_budgeted(0));
+        assert_eq!(Progress::Done(vec!['B', 'C']), system.next_budgeted(5));
+        assert_eq!(Progress::InProgress, system.next_budgeted(1));
+        assert_eq!(Progress::Terminated, system.next_budgeted(1));
+        assert_eq!(Progress::Terminated, system.next_budgeted(5));
+        assert_eq!(1, system.generation());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_next_within() {
+        let mut system = algae();
+        system.nth(19);
+        let expected = algae().nth(20).unwrap();
+
+        // each call with no time to spare still gets through one batch
+        let atoms = system.len();
+        let mut calls = 1;
+        while system.next_within(Duration::from_secs(0)) == Progress::InProgress {
+            calls += 1;
+        }
+        assert_eq!(atoms.div_ceil(ATOMS_PER_CLOCK_CHECK), calls);
+        assert_eq!(expected, system.current_state());
+        assert_eq!(Progress::Done(algae().nth(21).unwrap()), system.next_within(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_abandoned_by_next() {
+        let mut system = algae();
+        system.next();
+        assert_eq!(Progress::InProgress, system.next_budgeted(1));
+        assert_eq!(Some(vec!['A', 'B', 'A']), system.next());
+        assert_eq!(Progress::InProgress, system.next_budgeted(2));
+        assert_eq!(Progress::Done(vec!['A', 'B', 'A', 'A', 'B']), system.next_budgeted(1));
+    }
+}
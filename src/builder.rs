@@ -0,0 +1,119 @@
+//! A builder for assembling an `LSystem` from rules and an axiom.
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use {LSystem, MapRules};
+
+/// An error encountered while building an `LSystem`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildError {
+    /// `build` was called before an axiom was given
+    MissingAxiom,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::MissingAxiom => write!(f, "an L-system needs an axiom"),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// A builder for an `LSystem` driven by `MapRules`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LSystemBuilder, BuildError};
+///
+/// let mut system = LSystemBuilder::new()
+///     .rule_str('A', "AB")
+///     .rule_str('B', "A")
+///     .axiom(vec!['A'])
+///     .build()
+///     .unwrap();
+/// assert_eq!(Some(vec!['A', 'B']), system.next());
+///
+/// let missing = LSystemBuilder::new().rule(0, vec![0, 1]).build();
+/// assert_eq!(Some(BuildError::MissingAxiom), missing.err());
+/// ```
+pub struct LSystemBuilder<T: Hash + Eq> {
+    rules: MapRules<T>,
+    axiom: Option<Vec<T>>,
+}
+
+impl<T> LSystemBuilder<T> where T: Clone + Hash + Eq {
+    /// Create a new builder with no rules and no axiom.
+    pub fn new() -> LSystemBuilder<T> {
+        LSystemBuilder {
+            rules: MapRules::new(),
+            axiom: None,
+        }
+    }
+
+    /// Add a rule setting an atom to produce a vector.
+    pub fn rule(mut self, k: T, v: Vec<T>) -> LSystemBuilder<T> {
+        self.rules.set(k, v);
+        self
+    }
+
+    /// Set the axiom the L-system starts from.
+    pub fn axiom(mut self, axiom: Vec<T>) -> LSystemBuilder<T> {
+        self.axiom = Some(axiom);
+        self
+    }
+
+    /// Build the L-system.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::MissingAxiom` if no axiom was given.
+    pub fn build(self) -> Result<LSystem<T, MapRules<T>>, BuildError> {
+        match self.axiom {
+            Some(axiom) => Ok(LSystem::new(self.rules, axiom)),
+            None => Err(BuildError::MissingAxiom),
+        }
+    }
+}
+
+impl LSystemBuilder<char> {
+    /// Add a rule setting a char to produce the chars of a string.
+    pub fn rule_str(mut self, k: char, v: &str) -> LSystemBuilder<char> {
+        self.rules.set_str(k, v);
+        self
+    }
+}
+
+impl<T> Default for LSystemBuilder<T> where T: Clone + Hash + Eq {
+    fn default() -> LSystemBuilder<T> {
+        LSystemBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let system = LSystemBuilder::new()
+            .rule_str('1', "11")
+            .rule_str('0', "1[0]0")
+            .axiom(vec!['0'])
+            .build()
+            .unwrap();
+        let out: Vec<Vec<char>> = system.take(2).collect();
+        assert_eq!(vec!["1[0]0".chars().collect::<Vec<char>>(),
+                        "11[1[0]0]1[0]0".chars().collect()], out);
+    }
+
+    #[test]
+    fn test_empty_axiom_allowed() {
+        let mut system = LSystemBuilder::<u8>::new().axiom(vec![]).build().unwrap();
+        assert_eq!(None, system.next());
+    }
+}
@@ -0,0 +1,102 @@
+//! Layered production rules where the first ruleset to match wins.
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use LRules;
+
+/// A chain of rulesets tried in order, using the production from the first
+/// one that has a rule for an atom.
+///
+/// Putting an override ruleset in front of a base ruleset shadows the base
+/// rules for the atoms it covers, without changing the base.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{ChainRules, LRules, MapRules};
+///
+/// let mut base = MapRules::new();
+/// base.set_str('A', "AB");
+/// base.set_str('B', "A");
+/// let mut experiment = MapRules::new();
+/// experiment.set_str('B', "BB");
+///
+/// let rules = ChainRules::new().with(experiment).with(base);
+/// assert_eq!(Some(vec!['A', 'B']), rules.map(&'A'));
+/// assert_eq!(Some(vec!['B', 'B']), rules.map(&'B'));
+/// assert_eq!(None, rules.map(&'C'));
+/// ```
+pub struct ChainRules<T>(pub Vec<Box<dyn LRules<T>>>);
+
+impl<T> ChainRules<T> {
+    /// Create a new, empty chain.
+    pub fn new() -> ChainRules<T> {
+        ChainRules(Vec::new())
+    }
+
+    /// Add a ruleset to the end of the chain, where it is tried after all
+    /// the rulesets already in it.
+    pub fn push<R>(&mut self, rules: R) where R: LRules<T> + 'static {
+        self.0.push(Box::new(rules));
+    }
+
+    /// Add a ruleset to the end of the chain, returning the chain.
+    pub fn with<R>(mut self, rules: R) -> ChainRules<T> where R: LRules<T> + 'static {
+        self.push(rules);
+        self
+    }
+}
+
+impl<T> Default for ChainRules<T> {
+    fn default() -> ChainRules<T> {
+        ChainRules::new()
+    }
+}
+
+impl<T> LRules<T> for ChainRules<T> {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.0.iter().filter_map(|rules| rules.map(input)).next()
+    }
+
+    fn map_at(&self, state: &[T], index: usize) -> Option<Vec<T>> {
+        self.0.iter().filter_map(|rules| rules.map_at(state, index)).next()
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.0.iter().any(|rules| rules.rewrites_at(state, index))
+    }
+
+    fn start_generation(&self, generation: usize) {
+        for rules in self.0.iter() {
+            rules.start_generation(generation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    #[test]
+    fn test_override_shadows_base() {
+        let mut base = MapRules::new();
+        base.set_str('1', "11");
+        base.set_str('0', "1[0]0");
+        let mut overlay = MapRules::new();
+        overlay.set_str('1', "1");
+
+        let mut system = LSystem::new(ChainRules::new().with(overlay).with(base), vec!['0']);
+        system.next();
+        assert_eq!(Some("1[1[0]0]1[0]0".chars().collect()), system.next());
+    }
+
+    #[test]
+    fn test_empty_chain() {
+        let rules: ChainRules<char> = ChainRules::default();
+        assert_eq!(None, rules.map(&'A'));
+    }
+}
@@ -0,0 +1,311 @@
+//! An L-system that stores its state as one byte per atom, for systems with
+//! small alphabets.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use {LRules, LSystem, LSystemError, MapRules};
+
+/// A two-way mapping between symbols and the byte codes a `CompactLSystem`
+/// stores them as.  Codes are handed out in order of registration, starting
+/// from zero, so a codec holds at most `MAX_SYMBOLS` symbols.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::Codec;
+///
+/// let mut codec = Codec::new();
+/// assert_eq!(Some(0), codec.register('0'));
+/// assert_eq!(Some(1), codec.register('1'));
+/// assert_eq!(Some(0), codec.register('0'));
+///
+/// assert_eq!(Some(1), codec.encode(&'1'));
+/// assert_eq!(Some(&'1'), codec.decode(1));
+/// assert_eq!(None, codec.encode(&'['));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Codec<T: Hash + Eq> {
+    symbols: Vec<T>,
+    codes: HashMap<T, u8>,
+}
+
+impl<T> Codec<T> where T: Clone + Hash + Eq {
+    /// The largest number of symbols a codec can hold, one per byte value.
+    pub const MAX_SYMBOLS: usize = 256;
+
+    /// Create a new codec with no symbols.
+    pub fn new() -> Codec<T> {
+        Codec {
+            symbols: Vec::new(),
+            codes: HashMap::new(),
+        }
+    }
+
+    /// Give a symbol the next free code, returning its code, or return the
+    /// code it already has.  Returns `None` if the symbol is new and every
+    /// code is taken.
+    pub fn register(&mut self, symbol: T) -> Option<u8> {
+        if let Some(&code) = self.codes.get(&symbol) {
+            return Some(code);
+        }
+        if self.symbols.len() == Self::MAX_SYMBOLS {
+            return None;
+        }
+        let code = self.symbols.len() as u8;
+        self.codes.insert(symbol.clone(), code);
+        self.symbols.push(symbol);
+        Some(code)
+    }
+
+    /// The code for a symbol, if it has been registered.
+    pub fn encode(&self, symbol: &T) -> Option<u8> {
+        self.codes.get(symbol).cloned()
+    }
+
+    /// The symbol for a code, if one has been registered with it.
+    pub fn decode(&self, code: u8) -> Option<&T> {
+        self.symbols.get(code as usize)
+    }
+
+    /// The number of symbols registered.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Check whether no symbols have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Encode every symbol of a slice, collecting the symbols that have no
+    /// code, each listed once, as the error.
+    fn encode_all(&self, symbols: &[T]) -> Result<Vec<u8>, LSystemError<T>> {
+        let mut codes = Vec::with_capacity(symbols.len());
+        let mut unknown = Vec::new();
+        for symbol in symbols.iter() {
+            match self.encode(symbol) {
+                Some(code) => codes.push(code),
+                None if !unknown.contains(symbol) => unknown.push(symbol.clone()),
+                None => {},
+            }
+        }
+        if unknown.is_empty() {
+            Ok(codes)
+        } else {
+            Err(LSystemError::UnknownSymbols(unknown))
+        }
+    }
+}
+
+impl<T> Default for Codec<T> where T: Clone + Hash + Eq {
+    fn default() -> Codec<T> {
+        Codec::new()
+    }
+}
+
+/// Rules over byte codes, looked up by indexing a table rather than hashing.
+#[derive(Clone, Debug)]
+struct CodeRules {
+    productions: Vec<Option<Vec<u8>>>,
+}
+
+impl LRules<u8> for CodeRules {
+    fn map(&self, input: &u8) -> Option<Vec<u8>> {
+        self.productions[*input as usize].clone()
+    }
+}
+
+/// An L-system whose state is kept as one byte per atom, through a `Codec`.
+///
+/// A `char` takes four bytes, so for alphabets of up to `Codec::MAX_SYMBOLS`
+/// (256) symbols this holds a state of `char`s in a quarter of the memory,
+/// and larger atom types save more.  Expansion is done by an ordinary
+/// `LSystem` over the codes, so it behaves exactly like an `LSystem` with
+/// the same `MapRules`, and the rule lookups index a table instead of
+/// hashing.  Atoms are only decoded back into symbols when asked for, by
+/// `decode_state` or by iterating.
+///
+/// The codec can be built automatically from the symbols of the rules and
+/// axiom with `new`, or registered up front and given to `with_codec`, to
+/// fix which code each symbol gets.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{CompactLSystem, LSystem, MapRules};
+///
+/// let mut rules = MapRules::new();
+/// rules.set_str('1', "11");
+/// rules.set_str('0', "1[0]0");
+///
+/// let mut compact = CompactLSystem::new(&rules, &['0']).unwrap();
+/// let mut system = LSystem::new(rules, vec!['0']);
+/// assert_eq!(system.nth(5), compact.nth(5));
+/// assert_eq!(4, compact.codec().len());
+/// ```
+#[derive(Clone)]
+pub struct CompactLSystem<T: Hash + Eq> {
+    system: LSystem<u8, CodeRules>,
+    codec: Codec<T>,
+}
+
+impl<T> CompactLSystem<T> where T: Clone + Hash + Eq {
+    /// Create a compact L-system from rules and an axiom, registering each
+    /// symbol they use with a new codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LSystemError::AlphabetTooLarge` if the rules and axiom use
+    /// more symbols than a codec can hold.
+    pub fn new(rules: &MapRules<T>, axiom: &[T]) -> Result<CompactLSystem<T>, LSystemError<T>> {
+        let symbols = symbols_of(rules, axiom);
+        let mut codec = Codec::new();
+        for symbol in symbols.iter() {
+            if codec.register(symbol.clone()).is_none() {
+                let size = symbols.iter().collect::<HashSet<&T>>().len();
+                return Err(LSystemError::AlphabetTooLarge {
+                    limit: Codec::<T>::MAX_SYMBOLS,
+                    size,
+                });
+            }
+        }
+        CompactLSystem::with_codec(codec, rules, axiom)
+    }
+
+    /// Create a compact L-system from rules and an axiom, using the codes
+    /// already registered with `codec`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LSystemError::UnknownSymbols` listing the symbols of the
+    /// rules or axiom that the codec has no code for.
+    ///
+    /// ```
+    /// use lsystem::{Codec, CompactLSystem, LSystemError, MapRules};
+    ///
+    /// let mut codec = Codec::new();
+    /// for symbol in "AB".chars() {
+    ///     codec.register(symbol);
+    /// }
+    /// let rules = MapRules::from_str_rules(&[('A', "AB"), ('B', "A")]);
+    /// let mut system = CompactLSystem::with_codec(codec.clone(), &rules, &['A']).unwrap();
+    /// assert!(system.advance());
+    /// assert_eq!(&[0, 1], system.current_codes());
+    ///
+    /// let rules = MapRules::from_str_rules(&[('A', "AC")]);
+    /// let err = CompactLSystem::with_codec(codec, &rules, &['A']).err();
+    /// assert_eq!(Some(LSystemError::UnknownSymbols(vec!['C'])), err);
+    /// ```
+    pub fn with_codec(codec: Codec<T>, rules: &MapRules<T>, axiom: &[T]) -> Result<CompactLSystem<T>, LSystemError<T>> {
+        let symbols = symbols_of(rules, axiom);
+        codec.encode_all(&symbols)?;
+
+        let mut productions = vec![None; Codec::<T>::MAX_SYMBOLS];
+        for (k, v) in rules.iter() {
+            productions[codec.codes[k] as usize] = Some(codec.encode_all(v)?);
+        }
+        let axiom = codec.encode_all(axiom)?;
+        Ok(CompactLSystem {
+            system: LSystem::new(CodeRules { productions }, axiom),
+            codec,
+        })
+    }
+
+    /// The codec mapping symbols to the codes stored.
+    pub fn codec(&self) -> &Codec<T> {
+        &self.codec
+    }
+
+    /// The number of generations produced since the L-system was created or
+    /// last reset.
+    pub fn generation(&self) -> usize {
+        self.system.generation()
+    }
+
+    /// The number of atoms in the current state.
+    pub fn len(&self) -> usize {
+        self.system.len()
+    }
+
+    /// Check whether the current state has no atoms.
+    pub fn is_empty(&self) -> bool {
+        self.system.is_empty()
+    }
+
+    /// Borrow the current state as codes, without decoding it.
+    pub fn current_codes(&self) -> &[u8] {
+        self.system.current_state()
+    }
+
+    /// Decode the current state back into symbols.
+    pub fn decode_state(&self) -> Vec<T> {
+        self.current_codes().iter().map(|&code| self.codec.symbols[code as usize].clone()).collect()
+    }
+
+    /// Expand the L-system to its next generation in place, returning
+    /// whether any atom was expanded.
+    pub fn advance(&mut self) -> bool {
+        self.system.advance()
+    }
+
+    /// Reset the L-system back to its axiom.
+    pub fn reset(&mut self) {
+        self.system.reset();
+    }
+}
+
+/// Every symbol used by the axiom and rules, in order, with repeats.
+fn symbols_of<T>(rules: &MapRules<T>, axiom: &[T]) -> Vec<T> where T: Clone + Hash + Eq {
+    let mut symbols = axiom.to_vec();
+    for (k, v) in rules.iter() {
+        symbols.push(k.clone());
+        symbols.extend(v.iter().cloned());
+    }
+    symbols
+}
+
+impl<T> Iterator for CompactLSystem<T> where T: Clone + Hash + Eq {
+    type Item = Vec<T>;
+
+    /// Expand the next generation and return it decoded.
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.advance() {
+            Some(self.decode_state())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_lsystem() {
+        let rules = MapRules::from_str_rules(&[('F', "F[+F]F[-F]F"), ('X', "")]);
+        let axiom: Vec<char> = "XF".chars().collect();
+        let expected: Vec<Vec<char>> = LSystem::new(rules.clone(), axiom.clone()).take(4).collect();
+        let mut compact = CompactLSystem::new(&rules, &axiom).unwrap();
+        let actual: Vec<Vec<char>> = compact.by_ref().take(4).collect();
+        assert_eq!(expected, actual);
+        assert_eq!(4, compact.generation());
+
+        compact.reset();
+        assert_eq!(axiom, compact.decode_state());
+    }
+
+    #[test]
+    fn test_alphabet_too_large() {
+        let axiom: Vec<u32> = (0..300).collect();
+        let err = CompactLSystem::new(&MapRules::new(), &axiom).err();
+        assert_eq!(Some(LSystemError::AlphabetTooLarge { limit: 256, size: 300 }), err);
+
+        let mut codec = Codec::new();
+        for symbol in 0..256 {
+            assert_eq!(Some(symbol as u8), codec.register(symbol));
+        }
+        assert_eq!(None, codec.register(256));
+    }
+}
@@ -0,0 +1,241 @@
+//! Conditional stochastic productions for parametric L-systems, where a
+//! production applies only when a guard on the parameters holds, and one of
+//! the productions that apply is chosen at random.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use {LRules, Parametric, ParametricRules};
+
+type Guard = Box<dyn Fn(&[f64]) -> bool>;
+type Successor<S> = Box<dyn Fn(&[f64]) -> Vec<Parametric<S>>>;
+
+/// One production of a `ConditionalRules`, built up from its successor with
+/// an optional guard and a weight.
+///
+/// # Examples
+///
+/// The production `A(x) : x > 1 -> A(x-1)`, chosen with weight 2:
+///
+/// ```
+/// use lsystem::{ConditionalProduction, Parametric};
+///
+/// let production = ConditionalProduction::new(|x: &[f64]| vec![Parametric::new('A', vec![x[0] - 1.0])])
+///     .when(|x: &[f64]| x[0] > 1.0)
+///     .weight(2.0);
+/// ```
+pub struct ConditionalProduction<S> {
+    guard: Option<Guard>,
+    weight: f64,
+    successor: Successor<S>,
+}
+
+impl<S> ConditionalProduction<S> {
+    /// Create a production that computes its successor atoms with `f`.  It
+    /// applies to any parameters until given a guard, and has a weight of 1.
+    pub fn new<F>(f: F) -> ConditionalProduction<S> where F: Fn(&[f64]) -> Vec<Parametric<S>> + 'static {
+        ConditionalProduction {
+            guard: None,
+            weight: 1.0,
+            successor: Box::new(f),
+        }
+    }
+
+    /// Only apply the production to parameters for which `guard` holds.
+    pub fn when<G>(mut self, guard: G) -> ConditionalProduction<S> where G: Fn(&[f64]) -> bool + 'static {
+        self.guard = Some(Box::new(guard));
+        self
+    }
+
+    /// Set how likely the production is to be chosen, relative to the other
+    /// productions that apply to the same atom.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is negative or not finite.
+    pub fn weight(mut self, weight: f64) -> ConditionalProduction<S> {
+        assert!(weight.is_finite() && weight >= 0.0, "invalid production weight: {}", weight);
+        self.weight = weight;
+        self
+    }
+
+    fn applies(&self, params: &[f64]) -> bool {
+        self.weight > 0.0 && match self.guard {
+            Some(ref guard) => guard(params),
+            None => true,
+        }
+    }
+}
+
+/// A parametric ruleset of conditional stochastic productions, as described
+/// in section 1.10 of The Algorithmic Beauty of Plants.
+///
+/// Each symbol can have several productions.  To rewrite an atom, the
+/// productions whose guards hold for its parameters are kept, and one of
+/// them is chosen at random in proportion to its weight.  If none apply, the
+/// atom is left unchanged.  Like `StochasticRules`, the random number
+/// generator lives in a `RefCell`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{ConditionalProduction, ConditionalRules, LSystem, Parametric};
+///
+/// let mut rules = ConditionalRules::with_seed(1);
+/// // A(x) : x > 1 -> A(x-1) B, or A(x-1) C with twice the probability
+/// rules.add('A', ConditionalProduction::new(|x: &[f64]| vec![
+///     Parametric::new('A', vec![x[0] - 1.0]),
+///     Parametric::new('B', vec![]),
+/// ]).when(|x: &[f64]| x[0] > 1.0));
+/// rules.add('A', ConditionalProduction::new(|x: &[f64]| vec![
+///     Parametric::new('A', vec![x[0] - 1.0]),
+///     Parametric::new('C', vec![]),
+/// ]).when(|x: &[f64]| x[0] > 1.0).weight(2.0));
+///
+/// let mut system = LSystem::new(rules, vec![Parametric::new('A', vec![3.0])]);
+/// assert_eq!(2, system.by_ref().count());
+/// assert_eq!(3, system.current_state().len());
+/// ```
+pub struct ConditionalRules<S: Hash + Eq> {
+    productions: HashMap<S, Vec<ConditionalProduction<S>>>,
+    rng: RefCell<StdRng>,
+}
+
+impl<S> ConditionalRules<S> where S: Hash + Eq {
+    /// Create a new, empty ruleset with a randomly seeded generator.
+    pub fn new() -> ConditionalRules<S> {
+        ConditionalRules {
+            productions: HashMap::new(),
+            rng: RefCell::new(StdRng::from_entropy()),
+        }
+    }
+
+    /// Create a new, empty ruleset whose generator is seeded with `seed`, so
+    /// that the same rules and axiom always produce the same generations.
+    pub fn with_seed(seed: u64) -> ConditionalRules<S> {
+        ConditionalRules {
+            productions: HashMap::new(),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Add a production for a symbol, alongside any it already has.
+    pub fn add(&mut self, k: S, production: ConditionalProduction<S>) {
+        self.productions.entry(k).or_default().push(production);
+    }
+}
+
+impl<S> Default for ConditionalRules<S> where S: Hash + Eq {
+    fn default() -> ConditionalRules<S> {
+        ConditionalRules::new()
+    }
+}
+
+impl<S> ParametricRules<S> for ConditionalRules<S> where S: Hash + Eq {
+    fn map_params(&self, symbol: &S, params: &[f64]) -> Option<Vec<Parametric<S>>> {
+        let applicable: Vec<&ConditionalProduction<S>> = self.productions.get(symbol)?
+            .iter()
+            .filter(|p| p.applies(params))
+            .collect();
+        let last = applicable.last()?;
+        let total: f64 = applicable.iter().map(|p| p.weight).sum();
+        let r = self.rng.borrow_mut().gen::<f64>() * total;
+        let mut cumulative = 0.0;
+        for p in applicable.iter() {
+            cumulative += p.weight;
+            if r < cumulative {
+                return Some((p.successor)(params));
+            }
+        }
+        // rounding can leave the cumulative sum just shy of the total
+        Some((last.successor)(params))
+    }
+}
+
+impl<S> LRules<Parametric<S>> for ConditionalRules<S> where S: Hash + Eq {
+    fn map(&self, input: &Parametric<S>) -> Option<Vec<Parametric<S>>> {
+        self.map_params(&input.symbol, &input.params)
+    }
+
+    /// Check the guards only, without drawing from the generator or
+    /// building a successor.
+    fn rewrites_at(&self, state: &[Parametric<S>], index: usize) -> bool {
+        let atom = &state[index];
+        match self.productions.get(&atom.symbol) {
+            Some(productions) => productions.iter().any(|p| p.applies(&atom.params)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LSystem;
+
+    fn p(symbol: char, params: &[f64]) -> Parametric<char> {
+        Parametric::new(symbol, params.to_vec())
+    }
+
+    #[test]
+    fn test_guard() {
+        // A(x) : x > 1 -> A(x-1) B(x)
+        // A(x) : x <= 1 -> C
+        let mut rules = ConditionalRules::with_seed(0);
+        rules.add('A', ConditionalProduction::new(|x: &[f64]| vec![p('A', &[x[0] - 1.0]), p('B', x)])
+            .when(|x: &[f64]| x[0] > 1.0));
+        rules.add('A', ConditionalProduction::new(|_: &[f64]| vec![p('C', &[])])
+            .when(|x: &[f64]| x[0] <= 1.0));
+        let mut system = LSystem::new(rules, vec![p('A', &[2.5])]);
+
+        assert_eq!(Some(vec![p('A', &[1.5]), p('B', &[2.5])]), system.next());
+        assert_eq!(Some(vec![p('A', &[0.5]), p('B', &[1.5]), p('B', &[2.5])]), system.next());
+        assert_eq!(Some(vec![p('C', &[]), p('B', &[1.5]), p('B', &[2.5])]), system.next());
+        assert_eq!(None, system.next());
+    }
+
+    #[test]
+    fn test_samples_among_survivors() {
+        let mut rules = ConditionalRules::with_seed(5);
+        rules.add('X', ConditionalProduction::new(|_: &[f64]| vec![p('a', &[])]));
+        rules.add('X', ConditionalProduction::new(|_: &[f64]| vec![p('b', &[])]).weight(3.0));
+        rules.add('X', ConditionalProduction::new(|_: &[f64]| vec![p('c', &[])])
+            .when(|x: &[f64]| x[0] > 0.0));
+        rules.add('X', ConditionalProduction::new(|_: &[f64]| vec![p('d', &[])]).weight(0.0));
+
+        let mut seen = HashMap::new();
+        for _ in 0..400 {
+            let out = rules.map_params(&'X', &[0.0]).unwrap();
+            *seen.entry(out[0].symbol).or_insert(0) += 1;
+        }
+        assert!(!seen.contains_key(&'c') && !seen.contains_key(&'d'));
+        assert!(seen[&'b'] > seen[&'a'], "{:?}", seen);
+        assert_eq!(None, rules.map_params(&'Y', &[0.0]));
+    }
+
+    #[test]
+    fn test_seeded_reproducible() {
+        let build = |seed| {
+            let mut rules = ConditionalRules::with_seed(seed);
+            rules.add('A', ConditionalProduction::new(|x: &[f64]| vec![p('A', &[x[0] - 1.0]), p('B', &[])])
+                .when(|x: &[f64]| x[0] > 0.0));
+            rules.add('A', ConditionalProduction::new(|x: &[f64]| vec![p('B', &[]), p('A', &[x[0] - 1.0])])
+                .when(|x: &[f64]| x[0] > 0.0));
+            LSystem::new(rules, vec![p('A', &[6.0])])
+        };
+        let a: Vec<Vec<Parametric<char>>> = build(7).collect();
+        assert_eq!(6, a.len());
+
+        // checking for productions does not draw from the generator
+        let mut system = build(7);
+        let mut b = Vec::new();
+        while system.has_rewritable() {
+            b.extend(system.next());
+        }
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,475 @@
+//! Context-sensitive production rules, where the successor of an atom may
+//! depend on its left and right neighbors.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::mem;
+
+use LRules;
+
+/// A set of context-sensitive production rules.  In addition to the atom
+/// being rewritten, `map_context` receives its nearest left and right
+/// neighbors, which are `None` at either end of the state.
+pub trait LContextRules<T> {
+    /// perform a mapping of one atom to a string given its neighbors.  It
+    /// returns `Some(Vec<T>)` if a production rule matches the atom in this
+    /// context, or `None` if the atom should be left as it is.
+    fn map_context(&self, left: Option<&T>, input: &T, right: Option<&T>) -> Option<Vec<T>>;
+}
+
+/// A production ruleset keyed on an atom and, optionally, its left and right
+/// context.  These are the 1L and 2L systems of Lindenmayer's book.
+///
+/// A `None` context in a rule acts as a wildcard, so a rule set with
+/// `set(None, k, None, v)` behaves exactly like a context-free rule.  When
+/// several rules match the same atom, the most specific one wins: rules with
+/// both contexts are tried first, then left-context rules, then
+/// right-context rules, and finally the context-free rule.
+///
+/// A rule set with `set_class` matches any atom in a class of symbols,
+/// which saves writing the same rule for each of them.  Rules for the atom
+/// itself are more specific than any class rule, so they are all tried
+/// first, in the order above.  Class rules are then tried in the same order
+/// of contexts, and when several classes with the same contexts hold the
+/// atom, the smallest class wins, and of classes of the same size, the one
+/// set first.
+///
+/// # Examples
+///
+/// A signal `b` propagating rightward through a string of `a`s:
+///
+/// ```
+/// use lsystem::{LSystem, ContextMapRules, show};
+///
+/// let mut rules = ContextMapRules::new();
+/// rules.set_str(Some('b'), 'a', None, "b");
+/// rules.set_str(None, 'b', None, "a");
+/// let mut system = LSystem::new(rules, "baaa".chars().collect());
+///
+/// assert_eq!("abaa", show(&system.next().unwrap()));
+/// assert_eq!("aaba", show(&system.next().unwrap()));
+/// assert_eq!("aaab", show(&system.next().unwrap()));
+/// ```
+///
+/// Registering the bracket symbols makes context lookups follow the
+/// branching structure, as in The Algorithmic Beauty of Plants.  The left
+/// neighbor of the first atom in a branch is the atom the branch grows from,
+/// and a branch is skipped as a whole when looking along the axis it grows
+/// from, so the right neighbor of an atom before a `[` is the atom after the
+/// matching `]`.  The last atom of a branch has no right neighbor.
+///
+/// ```
+/// use lsystem::{LSystem, ContextMapRules, show};
+///
+/// let mut rules = ContextMapRules::new();
+/// rules.set_brackets('[', ']');
+/// rules.set_str(Some('b'), 'a', None, "b");
+/// rules.set_str(None, 'b', None, "a");
+/// let mut system = LSystem::new(rules, "ba[a]a".chars().collect());
+///
+/// assert_eq!("ab[a]a", show(&system.next().unwrap()));
+/// // the signal enters the branch and carries on along the main axis
+/// assert_eq!("aa[b]b", show(&system.next().unwrap()));
+/// ```
+///
+/// Other symbols that should not count as context, such as turtle commands,
+/// can be ignored in the same way:
+///
+/// ```
+/// use lsystem::{LSystem, ContextMapRules, show};
+///
+/// let mut rules = ContextMapRules::new();
+/// rules.set_ignore("+-".chars());
+/// rules.set_str(Some('b'), 'a', None, "b");
+/// rules.set_str(None, 'b', None, "a");
+/// let mut system = LSystem::new(rules, "b+a-a".chars().collect());
+///
+/// assert_eq!("a+b-a", show(&system.next().unwrap()));
+/// assert_eq!("a+a-b", show(&system.next().unwrap()));
+/// ```
+///
+/// A class rule lets a signal pass through any digit, while a rule for `0`
+/// itself absorbs it:
+///
+/// ```
+/// use lsystem::{LSystem, ContextMapRules, show};
+///
+/// let mut rules = ContextMapRules::new();
+/// rules.set_class(Some('*'), "0123456789".chars().collect(), None, vec!['*']);
+/// rules.set_str(Some('*'), '0', None, "0");
+/// rules.set_str(None, '*', None, "");
+/// let mut system = LSystem::new(rules, "*720".chars().collect());
+///
+/// assert_eq!("*20", show(&system.next().unwrap()));
+/// assert_eq!("*0", show(&system.next().unwrap()));
+/// assert_eq!("0", show(&system.next().unwrap()));
+/// ```
+pub struct ContextMapRules<T: Hash + Eq> {
+    productions: HashMap<T, Contexts<T>>,
+    classes: Vec<ClassRule<T>>,
+    brackets: Option<(T, T)>,
+    ignore: HashSet<T>,
+}
+
+/// The productions of one atom, keyed on its left and right context.
+type Contexts<T> = HashMap<(Option<T>, Option<T>), Vec<T>>;
+
+/// A context rule for any atom in a class of symbols.
+struct ClassRule<T: Hash + Eq> {
+    left: Option<T>,
+    class: HashSet<T>,
+    right: Option<T>,
+    production: Vec<T>,
+}
+
+impl<T> ContextMapRules<T> where T: Hash + Eq {
+    /// Create a new, empty ruleset.
+    pub fn new() -> ContextMapRules<T> {
+        ContextMapRules {
+            productions: HashMap::new(),
+            classes: Vec::new(),
+            brackets: None,
+            ignore: HashSet::new(),
+        }
+    }
+
+    /// Set an atom to produce a vector when it appears between the given
+    /// left and right context.  A context of `None` matches any neighbor.
+    pub fn set(&mut self, left: Option<T>, k: T, right: Option<T>, v: Vec<T>) -> Option<Vec<T>> {
+        self.productions.entry(k).or_default().insert((left, right), v)
+    }
+
+    /// Set every atom in `class` to produce a vector when it appears between
+    /// the given left and right context, unless a more specific rule
+    /// matches it.  This replaces any rule set before for the same class and
+    /// contexts, returning its production.
+    pub fn set_class(&mut self, left: Option<T>, class: HashSet<T>, right: Option<T>, v: Vec<T>) -> Option<Vec<T>> {
+        let existing = self.classes.iter_mut()
+            .find(|rule| rule.left == left && rule.class == class && rule.right == right);
+        if let Some(rule) = existing {
+            return Some(mem::replace(&mut rule.production, v));
+        }
+        self.classes.push(ClassRule {
+            left,
+            class,
+            right,
+            production: v,
+        });
+        None
+    }
+
+    /// Treat the given symbols as the start and end of a branch when looking
+    /// up the neighbors of an atom, so that context follows the branching
+    /// structure rather than the order of the state.
+    pub fn set_brackets(&mut self, open: T, close: T) {
+        self.brackets = Some((open, close));
+    }
+
+    /// Skip over the given symbols when looking up the neighbors of an atom,
+    /// replacing any symbols ignored before.
+    pub fn set_ignore<I>(&mut self, symbols: I) where I: IntoIterator<Item = T> {
+        self.ignore = symbols.into_iter().collect();
+    }
+
+    /// The left and right neighbors of the atom at `index`, as passed to
+    /// `map_context` when rewriting `state`.
+    ///
+    /// Ignored symbols are passed over.  With brackets set, looking left
+    /// steps out of the enclosing branch to the atom it grows from and skips
+    /// over any complete branches on the way, while looking right skips over
+    /// complete branches and stops at the end of the enclosing one.
+    ///
+    /// ```
+    /// use lsystem::ContextMapRules;
+    ///
+    /// let mut rules = ContextMapRules::new();
+    /// rules.set_brackets('[', ']');
+    /// let state: Vec<char> = "a[b]c[d]".chars().collect();
+    ///
+    /// assert_eq!((None, Some(&'c')), rules.neighbors(&state, 0));
+    /// assert_eq!((Some(&'a'), None), rules.neighbors(&state, 2));
+    /// assert_eq!((Some(&'a'), None), rules.neighbors(&state, 4));
+    /// ```
+    pub fn neighbors<'a>(&self, state: &'a [T], index: usize) -> (Option<&'a T>, Option<&'a T>) {
+        (self.left_of(state, index), self.right_of(state, index))
+    }
+
+    fn left_of<'a>(&self, state: &'a [T], index: usize) -> Option<&'a T> {
+        // the number of branches being skipped over
+        let mut depth = 0;
+        for atom in state[..index].iter().rev() {
+            match self.bracket(atom) {
+                Some(Bracket::Close) => depth += 1,
+                // at depth zero this steps out of the atom's own branch
+                Some(Bracket::Open) if depth == 0 => {},
+                Some(Bracket::Open) => depth -= 1,
+                None if depth == 0 && !self.ignore.contains(atom) => return Some(atom),
+                None => {},
+            }
+        }
+        None
+    }
+
+    fn right_of<'a>(&self, state: &'a [T], index: usize) -> Option<&'a T> {
+        let mut depth = 0;
+        for atom in state[index + 1..].iter() {
+            match self.bracket(atom) {
+                Some(Bracket::Open) => depth += 1,
+                // the end of the atom's own branch
+                Some(Bracket::Close) if depth == 0 => return None,
+                Some(Bracket::Close) => depth -= 1,
+                None if depth == 0 && !self.ignore.contains(atom) => return Some(atom),
+                None => {},
+            }
+        }
+        None
+    }
+
+    fn bracket(&self, atom: &T) -> Option<Bracket> {
+        match self.brackets {
+            Some((ref open, _)) if atom == open => Some(Bracket::Open),
+            Some((_, ref close)) if atom == close => Some(Bracket::Close),
+            _ => None,
+        }
+    }
+}
+
+enum Bracket {
+    Open,
+    Close,
+}
+
+impl ContextMapRules<char> {
+    /// Set an atom to produce the Vec<char> corresponding to a string when it
+    /// appears between the given left and right context.
+    pub fn set_str(&mut self, left: Option<char>, k: char, right: Option<char>, v: &str) -> Option<Vec<char>> {
+        self.set(left, k, right, v.chars().collect())
+    }
+}
+
+impl<T> Default for ContextMapRules<T> where T: Hash + Eq {
+    fn default() -> ContextMapRules<T> {
+        ContextMapRules::new()
+    }
+}
+
+impl<T> LContextRules<T> for ContextMapRules<T> where T: Clone + Hash + Eq {
+    fn map_context(&self, left: Option<&T>, input: &T, right: Option<&T>) -> Option<Vec<T>> {
+        if let Some(productions) = self.productions.get(input) {
+            // the key is edited in place, so each neighbor is cloned at most
+            // once, and only for atoms that have a rule
+            let mut key = (left.cloned(), right.cloned());
+            let mut found = productions.get(&key);
+            if found.is_none() {
+                let right = key.1.take();
+                found = productions.get(&key);
+                if found.is_none() {
+                    key = (None, right);
+                    found = productions.get(&key).or_else(|| productions.get(&(None, None)));
+                }
+            }
+            if let Some(v) = found {
+                return Some(v.clone());
+            }
+        }
+        let contexts = [(left, right), (left, None), (None, right), (None, None)];
+        for &(l, r) in contexts.iter() {
+            let best = self.classes.iter()
+                .filter(|rule| rule.left.as_ref() == l && rule.right.as_ref() == r && rule.class.contains(input))
+                .min_by_key(|rule| rule.class.len());
+            if let Some(rule) = best {
+                return Some(rule.production.clone());
+            }
+        }
+        None
+    }
+}
+
+impl<T> LRules<T> for ContextMapRules<T> where T: Clone + Hash + Eq {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.map_context(None, input, None)
+    }
+
+    fn map_at(&self, state: &[T], index: usize) -> Option<Vec<T>> {
+        let (left, right) = self.neighbors(state, index);
+        self.map_context(left, &state[index], right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use {LSystem, show};
+
+    /// An atom that counts how many times it has been cloned.
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Counted(char, Rc<CloneCount>);
+
+    #[derive(Debug, Default)]
+    struct CloneCount(Cell<usize>);
+
+    impl PartialEq for CloneCount {
+        fn eq(&self, _: &CloneCount) -> bool {
+            true
+        }
+    }
+
+    impl Eq for CloneCount {}
+
+    impl Hash for CloneCount {
+        fn hash<H: ::std::hash::Hasher>(&self, _: &mut H) {}
+    }
+
+    impl Clone for Counted {
+        fn clone(&self) -> Counted {
+            (self.1).0.set((self.1).0.get() + 1);
+            Counted(self.0, self.1.clone())
+        }
+    }
+
+    #[test]
+    fn test_lookup_clones() {
+        let count = Rc::new(CloneCount::default());
+        let atom = |c| Counted(c, count.clone());
+        let mut rules = ContextMapRules::new();
+        rules.set(Some(atom('a')), atom('b'), None, vec![]);
+        let state = vec![atom('a'), atom('b'), atom('c')];
+
+        // terminals cost nothing, and a match clones each neighbor once
+        assert_eq!(None, rules.map_at(&state, 0));
+        assert_eq!(None, rules.map_at(&state, 2));
+        assert_eq!(0, count.0.get());
+        assert_eq!(Some(vec![]), rules.map_at(&state, 1));
+        assert_eq!(2, count.0.get());
+    }
+
+    #[test]
+    fn test_specificity() {
+        let mut rules = ContextMapRules::new();
+        rules.set_str(None, 'a', None, "0");
+        rules.set_str(Some('x'), 'a', None, "1");
+        rules.set_str(None, 'a', Some('y'), "2");
+        rules.set_str(Some('x'), 'a', Some('y'), "3");
+
+        assert_eq!(Some(vec!['0']), rules.map_context(None, &'a', None));
+        assert_eq!(Some(vec!['1']), rules.map_context(Some(&'x'), &'a', Some(&'z')));
+        assert_eq!(Some(vec!['2']), rules.map_context(Some(&'z'), &'a', Some(&'y')));
+        assert_eq!(Some(vec!['3']), rules.map_context(Some(&'x'), &'a', Some(&'y')));
+        assert_eq!(None, rules.map_context(Some(&'x'), &'b', Some(&'y')));
+    }
+
+    #[test]
+    fn test_class_priority() {
+        let digits: HashSet<char> = "0123456789".chars().collect();
+        let odd: HashSet<char> = "13579".chars().collect();
+        let mut rules = ContextMapRules::new();
+        rules.set_class(Some('x'), digits.clone(), Some('y'), vec!['d']);
+        rules.set_class(Some('x'), odd.clone(), Some('y'), vec!['o']);
+        rules.set_class(None, digits.clone(), None, vec!['n']);
+        rules.set_str(None, '3', None, "3");
+
+        // the atom's own rule beats classes, even those with context
+        assert_eq!(Some(vec!['3']), rules.map_context(Some(&'x'), &'3', Some(&'y')));
+        // the smaller class wins at the same contexts
+        assert_eq!(Some(vec!['o']), rules.map_context(Some(&'x'), &'5', Some(&'y')));
+        assert_eq!(Some(vec!['d']), rules.map_context(Some(&'x'), &'4', Some(&'y')));
+        // a class with more context beats a smaller one with less
+        rules.set_class(None, "4".chars().collect(), None, vec!['4']);
+        assert_eq!(Some(vec!['d']), rules.map_context(Some(&'x'), &'4', Some(&'y')));
+        assert_eq!(Some(vec!['4']), rules.map_context(None, &'4', Some(&'y')));
+        assert_eq!(Some(vec!['n']), rules.map_context(None, &'5', Some(&'y')));
+        assert_eq!(None, rules.map_context(Some(&'x'), &'a', Some(&'y')));
+
+        assert_eq!(Some(vec!['d']), rules.set_class(Some('x'), digits, Some('y'), vec!['e']));
+        assert_eq!(Some(vec!['e']), rules.map_context(Some(&'x'), &'4', Some(&'y')));
+    }
+
+    #[test]
+    fn test_signal_propagation() {
+        let mut rules = ContextMapRules::new();
+        rules.set_str(Some('b'), 'a', None, "b");
+        rules.set_str(None, 'b', None, "a");
+        let mut system = LSystem::new(rules, "baaaa".chars().collect());
+
+        // productions are applied in parallel, so the signal only moves one
+        // atom per generation
+        assert_eq!("abaaa", show(&system.next().unwrap()));
+        assert_eq!("aabaa", show(&system.next().unwrap()));
+        assert_eq!("aaaba", show(&system.next().unwrap()));
+        assert_eq!("aaaab", show(&system.next().unwrap()));
+        assert_eq!("aaaaa", show(&system.next().unwrap()));
+        assert_eq!(None, system.next());
+    }
+
+    #[test]
+    fn test_skip_brackets() {
+        let mut rules = ContextMapRules::new();
+        rules.set_str(Some('b'), 'a', None, "X");
+        rules.set_str(None, 'b', Some('c'), "Y");
+        let state: Vec<char> = "b[a]c".chars().collect();
+
+        assert_eq!(None, rules.map_at(&state, 0));
+        assert_eq!(None, rules.map_at(&state, 2));
+        rules.set_brackets('[', ']');
+        assert_eq!(Some(vec!['Y']), rules.map_at(&state, 0));
+        assert_eq!(Some(vec!['X']), rules.map_at(&state, 2));
+    }
+
+    #[test]
+    fn test_nested_neighbors() {
+        let mut rules = ContextMapRules::new();
+        rules.set_brackets('[', ']');
+        rules.set_ignore("+".chars());
+        let state: Vec<char> = "a[b[+c]d]e[f]+g".chars().collect();
+        let expected = [
+            ('a', None, Some('e')),
+            ('b', Some('a'), Some('d')),
+            ('c', Some('b'), None),
+            ('d', Some('b'), None),
+            ('e', Some('a'), Some('g')),
+            ('f', Some('e'), None),
+            ('g', Some('e'), None),
+        ];
+        for &(atom, left, right) in expected.iter() {
+            let index = state.iter().position(|&c| c == atom).unwrap();
+            let (l, r) = rules.neighbors(&state, index);
+            assert_eq!((left, right), (l.cloned(), r.cloned()), "neighbors of {}", atom);
+        }
+    }
+
+    #[test]
+    fn test_acropetal_signal() {
+        // ABOP figure 1.31a: a signal b travels up from the base of a
+        // branching structure, into every branch
+        let mut rules = ContextMapRules::new();
+        rules.set_brackets('[', ']');
+        rules.set_ignore("+-".chars());
+        rules.set_str(Some('b'), 'a', None, "b");
+        let mut system = LSystem::new(rules, "ba[+a[-a]a]a[-a]a".chars().collect());
+
+        assert_eq!("bb[+a[-a]a]a[-a]a", show(&system.next().unwrap()));
+        assert_eq!("bb[+b[-a]a]b[-a]a", show(&system.next().unwrap()));
+        assert_eq!("bb[+b[-b]b]b[-b]b", show(&system.next().unwrap()));
+        assert_eq!(None, system.next());
+    }
+
+    #[test]
+    fn test_ignore() {
+        // ABOP section 1.8: a signal propagating through a branching
+        // structure, ignoring the turtle's turn and draw commands
+        let mut rules = ContextMapRules::new();
+        rules.set_brackets('[', ']');
+        rules.set_ignore("+-F".chars());
+        rules.set_str(Some('b'), 'a', None, "b");
+        rules.set_str(None, 'b', None, "a");
+        let mut system = LSystem::new(rules, "Fb+F-a[+a]".chars().collect());
+
+        assert_eq!("Fa+F-b[+a]", show(&system.next().unwrap()));
+        assert_eq!("Fa+F-a[+b]", show(&system.next().unwrap()));
+        assert_eq!("Fa+F-a[+a]", show(&system.next().unwrap()));
+        assert_eq!(None, system.next());
+    }
+}
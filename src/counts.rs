@@ -0,0 +1,61 @@
+//! Counting how often each symbol appears in a state.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use {LRules, LSystem};
+
+/// Count how many times each atom appears in a state.
+///
+/// ```
+/// use lsystem::symbol_counts;
+///
+/// let counts = symbol_counts(&['A', 'B', 'A']);
+/// assert_eq!(Some(&2), counts.get(&'A'));
+/// assert_eq!(Some(&1), counts.get(&'B'));
+/// assert_eq!(None, counts.get(&'C'));
+/// ```
+pub fn symbol_counts<T>(state: &[T]) -> HashMap<T, usize> where T: Hash + Eq + Clone {
+    let mut counts = HashMap::new();
+    for atom in state.iter() {
+        *counts.entry(atom.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Hash + Eq + Clone {
+    /// count how many times each atom appears in the current state.
+    pub fn current_counts(&self) -> HashMap<T, usize> {
+        symbol_counts(self.current_state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MapRules;
+
+    #[test]
+    fn test_algae_fibonacci() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AB");
+        rules.set_str('B', "A");
+        let mut system = LSystem::new(rules, vec!['A']);
+
+        let (mut fib, mut prev) = (1, 0);
+        for _ in 0..20 {
+            let counts = system.current_counts();
+            assert_eq!(fib, counts[&'A']);
+            assert_eq!(prev, counts.get(&'B').cloned().unwrap_or(0));
+            system.advance();
+            let next = fib + prev;
+            prev = fib;
+            fib = next;
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(symbol_counts::<u8>(&[]).is_empty());
+    }
+}
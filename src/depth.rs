@@ -0,0 +1,293 @@
+//! Production rules that depend on how deeply an atom is nested within
+//! bracketed branches.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use LRules;
+
+/// A set of production rules that can see the branch depth of the atom being
+/// rewritten, i.e. the number of unclosed opening brackets before it.
+pub trait LDepthRules<T> {
+    /// perform a mapping of one atom at the given branch depth.  It returns
+    /// `None` if the atom should be left as it is.
+    fn map_at_depth(&self, input: &T, depth: usize) -> Option<Vec<T>>;
+}
+
+/// A wrapper around a ruleset that stops applying it to atoms nested more
+/// than `max_depth` branches deep, so that deep branches stop growing.
+///
+/// The depth of an atom is the number of opening brackets before it that
+/// have not yet been closed; a bracket symbol itself counts at the depth
+/// outside it.  When driven by an `LSystem`, the depth is tracked in a single
+/// pass over the state.
+///
+/// `map_at` and `rewrites_at` look at one atom at a time, so they have to
+/// count the brackets before it.  They remember the depth of the last atom
+/// they looked at, and count on from there when asked about a later atom of
+/// the same state, so methods that go through a state an atom at a time,
+/// such as `LSystem::next_partial`, `LSystem::next_budgeted` and
+/// `LSystem::has_rewritable`, still take time in proportion to its length.
+/// This assumes the atoms before the last one looked at have not changed in
+/// the meantime, which holds for the methods of `LSystem`.  Asking about an
+/// earlier atom counts again from the start.  Without the `std` feature
+/// nothing is remembered, and every call counts from the start.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{DepthLimited, LSystem, MapRules, show};
+///
+/// let mut rules = MapRules::new();
+/// rules.set_str('F', "F[F]");
+/// let mut system = LSystem::new(DepthLimited::new(rules, 1), vec!['F']);
+///
+/// assert_eq!("F[F]", show(&system.next().unwrap()));
+/// assert_eq!("F[F][F[F]]", show(&system.next().unwrap()));
+/// assert_eq!("F[F][F[F]][F[F][F]]", show(&system.next().unwrap()));
+/// ```
+pub struct DepthLimited<R, T = char> {
+    rules: R,
+    max_depth: usize,
+    open: T,
+    close: T,
+    memo: DepthMemo,
+}
+
+/// The depth of the atom that `DepthLimited` last looked at, as the address
+/// of the state, the index of the atom and its depth.
+///
+/// The lock is only ever tried, so threads looking at atoms at the same
+/// time count from the start rather than wait for each other.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct DepthMemo(Mutex<Option<(usize, usize, usize)>>);
+
+#[cfg(feature = "std")]
+impl DepthMemo {
+    /// where to start counting from for the atom at `index`, as an index
+    /// and the depth there.
+    fn start(&self, state: usize, index: usize) -> (usize, usize) {
+        match self.0.try_lock().ok().and_then(|memo| *memo) {
+            Some((s, i, depth)) if s == state && i <= index => (i, depth),
+            _ => (0, 0),
+        }
+    }
+
+    fn remember(&self, state: usize, index: usize, depth: usize) {
+        if let Ok(mut memo) = self.0.try_lock() {
+            *memo = Some((state, index, depth));
+        }
+    }
+}
+
+/// Without `std` there is no lock to keep the memo `Sync`, so nothing is
+/// remembered.
+#[cfg(not(feature = "std"))]
+#[derive(Default)]
+struct DepthMemo;
+
+#[cfg(not(feature = "std"))]
+impl DepthMemo {
+    fn start(&self, _state: usize, _index: usize) -> (usize, usize) {
+        (0, 0)
+    }
+
+    fn remember(&self, _state: usize, _index: usize, _depth: usize) {}
+}
+
+impl<R> DepthLimited<R, char> {
+    /// Limit `rules` to atoms at most `max_depth` deep in `[` `]` branches.
+    pub fn new(rules: R, max_depth: usize) -> DepthLimited<R, char> {
+        DepthLimited::with_brackets(rules, max_depth, '[', ']')
+    }
+}
+
+impl<R, T> DepthLimited<R, T> where T: PartialEq {
+    /// the depth of the atom at `index` in `state`.
+    fn depth_at(&self, state: &[T], index: usize) -> usize {
+        let address = state.as_ptr() as usize;
+        let (start, mut depth) = self.memo.start(address, index);
+        for atom in state[start..index].iter() {
+            if *atom == self.open {
+                depth += 1;
+            } else if *atom == self.close {
+                depth = depth.saturating_sub(1);
+            }
+        }
+        self.memo.remember(address, index, depth);
+        depth
+    }
+}
+
+impl<R, T> DepthLimited<R, T> {
+    /// Limit `rules` to atoms at most `max_depth` deep, using the given
+    /// symbols to open and close branches.
+    pub fn with_brackets(rules: R, max_depth: usize, open: T, close: T) -> DepthLimited<R, T> {
+        DepthLimited {
+            rules,
+            max_depth,
+            open,
+            close,
+            memo: Default::default(),
+        }
+    }
+}
+
+impl<R, T> LDepthRules<T> for DepthLimited<R, T> where R: LRules<T> {
+    fn map_at_depth(&self, input: &T, depth: usize) -> Option<Vec<T>> {
+        if depth > self.max_depth {
+            None
+        } else {
+            self.rules.map(input)
+        }
+    }
+}
+
+impl<R, T> LRules<T> for DepthLimited<R, T> where R: LRules<T>, T: PartialEq {
+    /// Map an atom as though it were at depth zero, outside any branch,
+    /// since a lone atom has no brackets before it.
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.map_at_depth(input, 0)
+    }
+
+    fn map_at(&self, state: &[T], index: usize) -> Option<Vec<T>> {
+        if self.depth_at(state, index) > self.max_depth {
+            None
+        } else {
+            self.rules.map_at(state, index)
+        }
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.depth_at(state, index) <= self.max_depth && self.rules.rewrites_at(state, index)
+    }
+
+    fn map_state(&self, state: &[T]) -> Vec<Option<Vec<T>>> {
+        let mut depth: usize = 0;
+        let mut productions = Vec::with_capacity(state.len());
+        for (i, atom) in state.iter().enumerate() {
+            if depth > self.max_depth {
+                productions.push(None);
+            } else {
+                productions.push(self.rules.map_at(state, i));
+            }
+            if *atom == self.open {
+                depth += 1;
+            } else if *atom == self.close {
+                depth = depth.saturating_sub(1);
+            }
+        }
+        productions
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        self.rules.keys()
+    }
+
+    fn start_generation(&self, generation: usize) {
+        self.rules.start_generation(generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+    #[cfg(feature = "std")]
+    use StochasticRules;
+
+    fn max_depth(state: &[char]) -> usize {
+        let mut depth = 0;
+        let mut max = 0;
+        for c in state.iter() {
+            match *c {
+                '[' => {
+                    depth += 1;
+                    max = max.max(depth);
+                },
+                ']' => depth -= 1,
+                _ => {},
+            }
+        }
+        max
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F[+F]");
+        let mut system = LSystem::new(DepthLimited::new(rules, 3), vec!['F']);
+        let mut state = Vec::new();
+        for _ in 0..8 {
+            state = system.next().unwrap();
+        }
+        // atoms at depth 3 still expand, opening a fourth level, but nothing
+        // inside it grows any further
+        assert_eq!(4, max_depth(&state));
+    }
+
+    #[test]
+    fn test_map_at_matches_map_state() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "FF");
+        let rules = DepthLimited::new(rules, 1);
+        let state: Vec<char> = "F[F[F]F]F".chars().collect();
+        let all = rules.map_state(&state);
+        for (i, production) in all.iter().enumerate() {
+            assert_eq!(*production, rules.map_at(&state, i));
+            assert_eq!(production.is_some(), rules.rewrites_at(&state, i));
+        }
+        assert_eq!(None, all[4]);
+        assert_eq!(Some(vec!['F', 'F']), all[6]);
+        assert_eq!(None, rules.map_at_depth(&'F', 2));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_rewrites_at_does_not_draw() {
+        let build = || {
+            let mut rules = StochasticRules::with_seed(7);
+            rules.set_weighted('F', vec![
+                (1.0, "F[F]".chars().collect()),
+                (1.0, "[F]F".chars().collect()),
+            ]);
+            LSystem::new(DepthLimited::new(rules, 2), vec!['F'])
+        };
+        let a: Vec<Vec<char>> = build().take(6).collect();
+        let mut system = build();
+        let mut b = Vec::new();
+        while b.len() < 6 && system.has_rewritable() {
+            b.extend(system.next());
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_remembered_depth() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F[F]");
+        let build = |rules: MapRules<char>| LSystem::new(DepthLimited::new(rules, 1), vec!['F']);
+
+        // rewriting one atom at a time changes the state after the last atom
+        // looked at, but never before it, so remembering its depth gives the
+        // same results as counting from the start every time
+        let mut remembered = build(rules.clone());
+        let mut counted = build(rules.clone());
+        for _ in 0..60 {
+            counted.rules.memo = Default::default();
+            assert_eq!(counted.next_partial(1), remembered.next_partial(1));
+        }
+        assert!(remembered.generation() >= 3);
+
+        // going backwards counts again from the start
+        let limited = DepthLimited::new(rules, 1);
+        let state = remembered.current_state();
+        let all = limited.map_state(state);
+        for i in (0..state.len()).rev() {
+            assert_eq!(all[i], limited.map_at(state, i));
+        }
+    }
+}
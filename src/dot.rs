@@ -0,0 +1,105 @@
+//! Drawing a ruleset as a Graphviz graph.
+
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+use MapRules;
+
+impl<T> MapRules<T> where T: Clone + Display + Hash + Eq {
+    /// Describe the ruleset as a directed graph in Graphviz's DOT language,
+    /// with a node for each symbol and an edge from each symbol with a rule
+    /// to every symbol its production contains.
+    ///
+    /// Symbols that only appear in productions get a node too, drawn as a box
+    /// to mark them as terminals.  An edge for a symbol produced more than
+    /// once is drawn once, labelled with the count.  Nodes are named by
+    /// their `Display` text and listed in order of it, so the output is the
+    /// same from run to run.
+    ///
+    /// ```
+    /// use lsystem::MapRules;
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "AAC");
+    /// let dot = rules.to_dot();
+    ///
+    /// assert!(dot.starts_with("digraph rules {"));
+    /// assert!(dot.contains("\"C\" [shape=box];"));
+    /// assert!(dot.contains("\"A\" -> \"B\";"));
+    /// assert!(dot.contains("\"B\" -> \"A\" [label=\"2\"];"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let (symbols, matrix) = self.growth_matrix();
+        let names: Vec<String> = symbols.iter().map(|s| quote(&s.to_string())).collect();
+        let mut order: Vec<usize> = (0..symbols.len()).collect();
+        order.sort_by(|&i, &j| names[i].cmp(&names[j]));
+
+        let mut out = String::new();
+        // writing to a String cannot fail
+        let _ = writeln!(out, "digraph rules {{");
+        for &i in order.iter() {
+            if self.productions.contains_key(&symbols[i]) {
+                let _ = writeln!(out, "    {};", names[i]);
+            } else {
+                let _ = writeln!(out, "    {} [shape=box];", names[i]);
+            }
+        }
+        for &i in order.iter().filter(|&&i| self.productions.contains_key(&symbols[i])) {
+            for &j in order.iter() {
+                match matrix[i][j] {
+                    0 => {},
+                    1 => {
+                        let _ = writeln!(out, "    {} -> {};", names[i], names[j]);
+                    },
+                    n => {
+                        let _ = writeln!(out, "    {} -> {} [label=\"{}\"];", names[i], names[j], n);
+                    },
+                }
+            }
+        }
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+/// Quote a node name for DOT, escaping quotes and backslashes.
+fn quote(name: &str) -> String {
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F[+F]F\"");
+        rules.set_str('X', "");
+        let expected = r#"digraph rules {
+    "+" [shape=box];
+    "F";
+    "X";
+    "[" [shape=box];
+    "\"" [shape=box];
+    "]" [shape=box];
+    "F" -> "+";
+    "F" -> "F" [label="3"];
+    "F" -> "[";
+    "F" -> "\"";
+    "F" -> "]";
+}
+"#;
+        assert_eq!(expected, rules.to_dot());
+    }
+}
@@ -0,0 +1,145 @@
+//! Edge rewriting, where only the symbols that draw the edges of a figure
+//! are rewritten.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use LRules;
+
+/// A wrapper around a ruleset that only applies it to a chosen set of edge
+/// symbols, leaving every other atom as it is.
+///
+/// L-systems that draw with a turtle are usually written in one of two
+/// styles.  In *node rewriting*, drawing symbols such as `F` are terminals
+/// and the figure grows from non-drawing nodes such as `X`, which expand
+/// into new branches.  In *edge rewriting*, the drawing symbols themselves
+/// are the variables, and each edge is replaced by a smaller polygon of
+/// edges, as in the Koch island or the Sierpinski arrowhead.  Since
+/// `LSystem` rewrites every atom that has a rule, a ruleset that mixes both
+/// styles, or that has rules for structural symbols like `f`, can be
+/// restricted to edge rewriting by wrapping it.
+///
+/// Brackets, turns and any symbol not in the edge set are always carried
+/// over unchanged, even if the wrapped ruleset has a rule for them.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{EdgeRewriteRules, LSystem, MapRules, show};
+///
+/// // a left edge F and a right edge G draw the Sierpinski arrowhead, and X
+/// // is a node rule that edge rewriting leaves alone
+/// let mut rules = MapRules::new();
+/// rules.set_str('F', "G+F+G");
+/// rules.set_str('G', "F-G-F");
+/// rules.set_str('X', "F[X]");
+/// let edges = EdgeRewriteRules::new(rules, vec!['F', 'G']);
+/// let mut system = LSystem::new(edges, "FX".chars().collect());
+///
+/// assert_eq!("G+F+GX", show(&system.next().unwrap()));
+/// assert_eq!("F-G-F+G+F+G+F-G-FX", show(&system.next().unwrap()));
+/// ```
+pub struct EdgeRewriteRules<R, T = char> {
+    rules: R,
+    edges: Vec<T>,
+}
+
+impl<R, T> EdgeRewriteRules<R, T> {
+    /// Apply `rules` only to the atoms in `edges`.
+    pub fn new(rules: R, edges: Vec<T>) -> EdgeRewriteRules<R, T> {
+        EdgeRewriteRules {
+            rules,
+            edges,
+        }
+    }
+
+    /// The symbols that are rewritten.
+    pub fn edges(&self) -> &[T] {
+        &self.edges
+    }
+}
+
+impl<R> EdgeRewriteRules<R, char> {
+    /// Apply `rules` only to the ASCII uppercase letters, the usual
+    /// convention for edge symbols, treating lowercase letters and
+    /// punctuation as structural.
+    pub fn uppercase(rules: R) -> EdgeRewriteRules<R, char> {
+        EdgeRewriteRules::new(rules, ('A'..='Z').collect())
+    }
+}
+
+impl<R, T> EdgeRewriteRules<R, T> where T: PartialEq {
+    fn is_edge(&self, atom: &T) -> bool {
+        self.edges.contains(atom)
+    }
+}
+
+impl<R, T> LRules<T> for EdgeRewriteRules<R, T> where R: LRules<T>, T: PartialEq {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        if self.is_edge(input) {
+            self.rules.map(input)
+        } else {
+            None
+        }
+    }
+
+    fn map_at(&self, state: &[T], index: usize) -> Option<Vec<T>> {
+        if self.is_edge(&state[index]) {
+            self.rules.map_at(state, index)
+        } else {
+            None
+        }
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.is_edge(&state[index]) && self.rules.rewrites_at(state, index)
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        let keys = self.rules.keys()?;
+        Some(keys.into_iter().filter(|k| self.is_edge(k)).collect())
+    }
+
+    fn start_generation(&self, generation: usize) {
+        self.rules.start_generation(generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    #[test]
+    fn test_koch_island() {
+        // the quadratic Koch island, with a rule for the move symbol f that
+        // edge rewriting has to ignore
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F-F+F+FF-F-F+F");
+        rules.set_str('f', "ff");
+        let edges = EdgeRewriteRules::uppercase(rules.clone());
+        assert!(!edges.rewrites_at(&['f'], 0));
+        assert!(edges.rewrites_at(&['F'], 0));
+
+        let axiom: Vec<char> = "F-F-F-FfF".chars().collect();
+        let mut system = LSystem::new(edges, axiom.clone());
+        let state = system.nth(1).unwrap();
+        assert_eq!(5 * 8 * 8, state.iter().filter(|&&c| c == 'F').count());
+        assert_eq!(1, state.iter().filter(|&&c| c == 'f').count());
+
+        let mut plain = LSystem::new(rules, axiom);
+        let state = plain.nth(1).unwrap();
+        assert_eq!(4, state.iter().filter(|&&c| c == 'f').count());
+    }
+
+    #[test]
+    fn test_edge_set() {
+        let mut rules = MapRules::new();
+        rules.set(1, vec![1, 2]);
+        rules.set(2, vec![1]);
+        let rules = EdgeRewriteRules::new(rules, vec![2]);
+        assert_eq!(&[2], rules.edges());
+        assert_eq!(None, rules.map(&1));
+        assert_eq!(Some(vec![1]), rules.map_at(&[1, 2], 1));
+    }
+}
@@ -0,0 +1,133 @@
+//! A single error type shared by the fallible parts of the crate.
+
+use std::error::Error;
+use std::fmt;
+
+use turtle::BracketError;
+use {BuildError, ParseError};
+
+/// An error from any fallible operation on an L-system.  `T` is the atom
+/// type, used to report symbols that fall outside a vocabulary.
+///
+/// The more specific `ParseError`, `BuildError` and `BracketError` convert
+/// into it with `?`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LSystemError, MapRules};
+///
+/// fn load(text: &str) -> Result<MapRules<char>, LSystemError> {
+///     let rules = MapRules::from_rules_str(text)?;
+///     Ok(rules)
+/// }
+///
+/// assert!(load("A -> AB").is_ok());
+/// match load("A AB") {
+///     Err(LSystemError::Parse(e)) => assert_eq!(1, e.line),
+///     _ => panic!("expected a parse error"),
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum LSystemError<T = char> {
+    /// rule text could not be parsed
+    Parse(ParseError),
+    /// an L-system could not be built
+    Build(BuildError),
+    /// a state could not be drawn because its brackets are unbalanced
+    Brackets(BracketError),
+    /// symbols were used that are not in the vocabulary, each listed once
+    UnknownSymbols(Vec<T>),
+    /// a state grew past a length limit
+    LengthExceeded {
+        /// the maximum number of atoms allowed
+        limit: usize,
+        /// the number of atoms the state would have had
+        length: usize,
+    },
+    /// the next generation would have had more atoms than a limit, which
+    /// may be the most a `usize` can count
+    LengthOverflow {
+        /// the maximum number of atoms allowed
+        limit: usize,
+    },
+    /// more symbols were used than a compact encoding can hold
+    AlphabetTooLarge {
+        /// the maximum number of symbols allowed
+        limit: usize,
+        /// the number of symbols used
+        size: usize,
+    },
+}
+
+impl<T> fmt::Display for LSystemError<T> where T: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LSystemError::Parse(ref e) => write!(f, "{}", e),
+            LSystemError::Build(ref e) => write!(f, "{}", e),
+            LSystemError::Brackets(ref e) => write!(f, "{}", e),
+            LSystemError::UnknownSymbols(ref symbols) =>
+                write!(f, "symbols not in the vocabulary: {:?}", symbols),
+            LSystemError::LengthExceeded { limit, length } =>
+                write!(f, "state of {} atoms exceeds the limit of {}", length, limit),
+            LSystemError::LengthOverflow { limit } =>
+                write!(f, "next generation would have more than {} atoms", limit),
+            LSystemError::AlphabetTooLarge { limit, size } =>
+                write!(f, "alphabet of {} symbols exceeds the limit of {}", size, limit),
+        }
+    }
+}
+
+impl<T> Error for LSystemError<T> where T: fmt::Debug {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LSystemError::Parse(ref e) => Some(e),
+            LSystemError::Build(ref e) => Some(e),
+            LSystemError::Brackets(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<T> From<ParseError> for LSystemError<T> {
+    fn from(e: ParseError) -> LSystemError<T> {
+        LSystemError::Parse(e)
+    }
+}
+
+impl<T> From<BuildError> for LSystemError<T> {
+    fn from(e: BuildError) -> LSystemError<T> {
+        LSystemError::Build(e)
+    }
+}
+
+impl<T> From<BracketError> for LSystemError<T> {
+    fn from(e: BracketError) -> LSystemError<T> {
+        LSystemError::Brackets(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LSystemBuilder;
+
+    #[test]
+    fn test_from_build_error() {
+        fn build() -> Result<(), LSystemError<u8>> {
+            LSystemBuilder::<u8>::new().build()?;
+            Ok(())
+        }
+        let err = build().unwrap_err();
+        assert_eq!(LSystemError::Build(BuildError::MissingAxiom), err);
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_display() {
+        let err: LSystemError = LSystemError::UnknownSymbols(vec!['X', 'Y']);
+        assert_eq!("symbols not in the vocabulary: ['X', 'Y']", err.to_string());
+        let err: LSystemError = LSystemError::LengthExceeded { limit: 10, length: 16 };
+        assert_eq!("state of 16 atoms exceeds the limit of 10", err.to_string());
+    }
+}
@@ -0,0 +1,60 @@
+//! Production rules computed by a closure.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use LRules;
+
+/// A production ruleset that computes each production with a function rather
+/// than looking it up, for atoms whose productions follow a formula.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{FnRules, LSystem};
+///
+/// let rules = FnRules::new(|x: &u32| if *x < 10 { Some(vec![*x + 1, *x + 1]) } else { None });
+/// let mut system = LSystem::new(rules, vec![8]);
+///
+/// assert_eq!(Some(vec![9, 9]), system.next());
+/// assert_eq!(Some(vec![10, 10, 10, 10]), system.next());
+/// assert_eq!(None, system.next());
+/// ```
+pub struct FnRules<T, F> where F: Fn(&T) -> Option<Vec<T>> {
+    f: F,
+    atom: PhantomData<fn(&T)>,
+}
+
+impl<T, F> FnRules<T, F> where F: Fn(&T) -> Option<Vec<T>> {
+    /// Create a ruleset from a function giving the production for an atom,
+    /// or `None` if it is left unchanged.
+    pub fn new(f: F) -> FnRules<T, F> {
+        FnRules {
+            f,
+            atom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> LRules<T> for FnRules<T, F> where F: Fn(&T) -> Option<Vec<T>> {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        (self.f)(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LSystem;
+
+    #[test]
+    fn test_arithmetic_expansion() {
+        let rules = FnRules::new(|x: &i32| match *x {
+            0 | 1 => None,
+            n => Some(vec![n / 2, n - n / 2]),
+        });
+        let system = LSystem::new(rules, vec![5]);
+        assert_eq!(Some(vec![1, 1, 1, 1, 1]), system.last());
+    }
+}
@@ -0,0 +1,150 @@
+//! Production rules over extended grapheme clusters, for alphabets of emoji
+//! and combined characters that a `char` cannot hold.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use {LRules, MapRules};
+
+/// Split a string into its extended grapheme clusters, the characters a
+/// reader would see, each as its own `String`.  This is how `GraphemeRules`
+/// reads productions, and gives the axiom for an L-system over them.
+///
+/// ```
+/// use lsystem::graphemes;
+///
+/// // a farmer emoji is three chars joined into one grapheme
+/// assert_eq!(vec!["👩‍🌾", "🌱"], graphemes("👩‍🌾🌱"));
+/// // and so is an e with a combining accent
+/// assert_eq!(vec!["e\u{301}", "t", "e"], graphemes("e\u{301}te"));
+/// ```
+pub fn graphemes(s: &str) -> Vec<String> {
+    s.graphemes(true).map(|g| g.to_string()).collect()
+}
+
+/// A production ruleset over grapheme clusters, stored as `String`s.
+///
+/// A `char` is a single Unicode scalar value, so emoji sequences, flags and
+/// letters with combining marks, which take several, are split apart by a
+/// `MapRules<char>`.  `GraphemeRules` splits its productions into grapheme
+/// clusters instead, so each of these is one atom.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{graphemes, show_with, GraphemeRules, LSystem};
+///
+/// let mut rules = GraphemeRules::new();
+/// rules.set("👩‍🌾", "👩‍🌾🌱");
+/// rules.set("🌱", "🌳");
+/// let mut system = LSystem::new(rules, graphemes("👩‍🌾🌱"));
+/// assert_eq!(2, system.len());
+///
+/// let state = system.nth(1).unwrap();
+/// assert_eq!("👩‍🌾🌱🌳🌳", show_with(&state, ""));
+/// assert_eq!(4, state.len());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GraphemeRules {
+    rules: MapRules<String>,
+}
+
+impl GraphemeRules {
+    /// Create a new, empty ruleset.
+    pub fn new() -> GraphemeRules {
+        GraphemeRules {
+            rules: MapRules::new(),
+        }
+    }
+
+    /// Create a ruleset from pairs of graphemes and the strings they produce.
+    /// Later pairs overwrite earlier ones for the same grapheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key is not a single grapheme cluster.
+    pub fn from_str_rules(pairs: &[(&str, &str)]) -> GraphemeRules {
+        let mut rules = GraphemeRules::new();
+        for &(k, v) in pairs.iter() {
+            rules.set(k, v);
+        }
+        rules
+    }
+
+    /// Set a grapheme to produce the grapheme clusters of a string,
+    /// returning the production it replaces, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is not a single grapheme cluster, since a rule for it
+    /// could never match an atom.
+    pub fn set(&mut self, k: &str, v: &str) -> Option<Vec<String>> {
+        assert!(k.graphemes(true).count() == 1, "rule key is not a single grapheme: {:?}", k);
+        self.rules.set(k.to_string(), graphemes(v))
+    }
+
+    /// Get the production for a grapheme, if it has one.
+    pub fn get(&self, k: &str) -> Option<&Vec<String>> {
+        self.rules.get(k)
+    }
+
+    /// The number of graphemes that have a rule.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check whether the ruleset has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl LRules<String> for GraphemeRules {
+    fn map(&self, input: &String) -> Option<Vec<String>> {
+        self.rules.map(input)
+    }
+
+    fn rewrites_at(&self, state: &[String], index: usize) -> bool {
+        self.rules.rewrites_at(state, index)
+    }
+
+    fn map_all_into(&self, state: &[String], next: &mut Vec<String>) -> bool {
+        self.rules.map_all_into(state, next)
+    }
+
+    fn map_all_drain(&self, state: &mut Vec<String>, next: &mut Vec<String>) -> bool {
+        self.rules.map_all_drain(state, next)
+    }
+
+    fn keys(&self) -> Option<Vec<&String>> {
+        self.rules.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {show, show_with, LSystem};
+
+    #[test]
+    fn test_combining_marks() {
+        // the flag is two regional indicators, the accent a combining mark
+        let rules = GraphemeRules::from_str_rules(&[("🇸🇪", "🇸🇪e\u{301}"), ("e\u{301}", "")]);
+        let axiom = graphemes("🇸🇪e\u{301}");
+        assert_eq!(4, "🇸🇪e\u{301}".chars().count());
+        assert_eq!(2, axiom.len());
+
+        let generations: Vec<String> = LSystem::new(rules, axiom).take(2).map(|g| show_with(&g, "")).collect();
+        assert_eq!(vec!["🇸🇪e\u{301}", "🇸🇪e\u{301}"], generations);
+
+        // split as chars, the accent is a separate atom and never matches
+        let chars = MapRules::from_str_rules(&[('e', "E")]);
+        let state = LSystem::new(chars, "e\u{301}".chars().collect()).next().unwrap();
+        assert_eq!("E\u{301}", show(&state));
+    }
+
+    #[test]
+    #[should_panic(expected = "not a single grapheme")]
+    fn test_key_must_be_one_grapheme() {
+        GraphemeRules::new().set("ab", "b");
+    }
+}
@@ -0,0 +1,191 @@
+//! Estimating the asymptotic growth of context-free L-systems.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use MapRules;
+
+impl<T> MapRules<T> where T: Clone + Hash + Eq {
+    /// The symbol-count transition matrix of the ruleset.  Returns the list
+    /// of symbols, made of every atom with a rule followed by every other
+    /// atom its productions contain, and a square matrix in which entry
+    /// `[i][j]` counts how many of symbol `j` are produced from one symbol
+    /// `i`.  A symbol with no rule produces only itself.
+    ///
+    /// The order of the symbols is arbitrary, but the same list orders both
+    /// the rows and the columns.  The dominant eigenvalue of the matrix is
+    /// the factor by which the state grows in the long run.
+    ///
+    /// ```
+    /// use lsystem::MapRules;
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let (symbols, matrix) = rules.growth_matrix();
+    ///
+    /// let a = symbols.iter().position(|&c| c == 'A').unwrap();
+    /// let b = symbols.iter().position(|&c| c == 'B').unwrap();
+    /// assert_eq!(1, matrix[a][a]);
+    /// assert_eq!(1, matrix[a][b]);
+    /// assert_eq!(1, matrix[b][a]);
+    /// assert_eq!(0, matrix[b][b]);
+    /// ```
+    pub fn growth_matrix(&self) -> (Vec<T>, Vec<Vec<usize>>) {
+        let mut symbols: Vec<T> = self.productions.keys().cloned().collect();
+        let mut index: HashMap<T, usize> = symbols.iter().cloned()
+            .enumerate()
+            .map(|(i, s)| (s, i))
+            .collect();
+        for production in self.productions.values() {
+            for atom in production.iter() {
+                if !index.contains_key(atom) {
+                    index.insert(atom.clone(), symbols.len());
+                    symbols.push(atom.clone());
+                }
+            }
+        }
+
+        let n = symbols.len();
+        let mut matrix = vec![vec![0; n]; n];
+        for (i, symbol) in symbols.iter().enumerate() {
+            match self.productions.get(symbol) {
+                Some(production) => {
+                    for atom in production.iter() {
+                        matrix[i][index[atom]] += 1;
+                    }
+                },
+                None => matrix[i][i] = 1,
+            }
+        }
+        (symbols, matrix)
+    }
+
+    /// Estimate the asymptotic factor by which the state grows each
+    /// generation, as the dominant eigenvalue of `growth_matrix` found by
+    /// power iteration.  Returns `None` if the ruleset is empty or the
+    /// iteration does not converge.
+    ///
+    /// ```
+    /// use lsystem::MapRules;
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let golden_ratio = (1.0 + 5f64.sqrt()) / 2.0;
+    /// assert!((rules.dominant_growth_rate().unwrap() - golden_ratio).abs() < 1e-9);
+    /// ```
+    pub fn dominant_growth_rate(&self) -> Option<f64> {
+        let (_, matrix) = self.growth_matrix();
+        let n = matrix.len();
+        if n == 0 {
+            return None;
+        }
+
+        // The dominant eigenvalue of a non-negative matrix is the largest
+        // among those of its strongly connected blocks.  Power iteration is
+        // only guaranteed to converge within one block, so find them from the
+        // transitive closure of the production graph.
+        let mut reach: Vec<Vec<bool>> = matrix.iter()
+            .map(|row| row.iter().map(|&count| count > 0).collect())
+            .collect();
+        for k in 0..n {
+            let via = reach[k].clone();
+            for row in reach.iter_mut() {
+                if row[k] {
+                    for (r, &v) in row.iter_mut().zip(via.iter()) {
+                        *r = *r || v;
+                    }
+                }
+            }
+        }
+
+        let mut assigned = vec![false; n];
+        let mut rate: f64 = 0.0;
+        for i in 0..n {
+            if assigned[i] {
+                continue;
+            }
+            let block: Vec<usize> = (0..n)
+                .filter(|&j| j == i || (reach[i][j] && reach[j][i]))
+                .collect();
+            for &j in block.iter() {
+                assigned[j] = true;
+            }
+            rate = rate.max(block_rate(&matrix, &block)?);
+        }
+        Some(rate)
+    }
+}
+
+/// The dominant eigenvalue of the strongly connected block of `matrix` made
+/// of the rows and columns in `block`, by power iteration.
+fn block_rate(matrix: &[Vec<usize>], block: &[usize]) -> Option<f64> {
+    // iterating on M + I shifts every eigenvalue by one, which keeps
+    // periodic blocks such as A -> B, B -> A from oscillating
+    let mut x = vec![1.0 / block.len() as f64; block.len()];
+    let mut rate = -1.0;
+    for _ in 0..100_000 {
+        let mut next = x.clone();
+        for (a, &i) in block.iter().enumerate() {
+            for (b, &j) in block.iter().enumerate() {
+                next[a] += matrix[i][j] as f64 * x[b];
+            }
+        }
+        let total: f64 = next.iter().sum();
+        let estimate = total - 1.0;
+        for v in next.iter_mut() {
+            *v /= total;
+        }
+        x = next;
+        if (estimate - rate).abs() < 1e-13 * estimate.max(1.0) {
+            return Some(estimate);
+        }
+        rate = estimate;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(expected: f64, actual: Option<f64>) {
+        let actual = actual.unwrap();
+        assert!((expected - actual).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn test_growth_rates() {
+        let pythagoras = MapRules::from_str_rules(&[('1', "11"), ('0', "1[0]0")]);
+        assert_near(2.0, pythagoras.dominant_growth_rate());
+
+        let swap = MapRules::from_str_rules(&[('A', "B"), ('B', "A")]);
+        assert_near(1.0, swap.dominant_growth_rate());
+
+        let koch = MapRules::from_str_rules(&[('F', "F+F-F-F+F")]);
+        assert_near(5.0, koch.dominant_growth_rate());
+
+        let dying = MapRules::from_str_rules(&[('A', "BC"), ('B', "")]);
+        assert_near(1.0, dying.dominant_growth_rate());
+
+        let vanishing = MapRules::from_str_rules(&[('A', "")]);
+        assert_near(0.0, vanishing.dominant_growth_rate());
+
+        let empty: MapRules<char> = MapRules::new();
+        assert_eq!(None, empty.dominant_growth_rate());
+    }
+
+    #[test]
+    fn test_terminals_in_matrix() {
+        let rules = MapRules::from_str_rules(&[('F', "F[+F]")]);
+        let (symbols, matrix) = rules.growth_matrix();
+        assert_eq!(4, symbols.len());
+        assert_eq!('F', symbols[0]);
+        assert_eq!(vec![2, 1, 1, 1], matrix[0]);
+        for (i, row) in matrix.iter().enumerate().skip(1) {
+            assert_eq!(1, row[i]);
+            assert_eq!(1, row.iter().sum::<usize>());
+        }
+    }
+}
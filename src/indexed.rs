@@ -0,0 +1,148 @@
+//! Production rules that depend on where an atom is in the state, for
+//! regular variation such as alternating leaves.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use {LRules, RuleKey, RuleMap};
+
+/// A set of production rules that can see the index of the atom being
+/// rewritten in the state.
+///
+/// This is the position in the generation being rewritten, counting from
+/// zero, before any atom of it has been expanded.  An `LSystem` passes it
+/// through `LRules::map_at`.
+pub trait IndexedRules<T> {
+    /// perform a mapping of the atom at `index`.  It returns `None` if the
+    /// atom should be left as it is.
+    fn map_indexed(&self, atom: &T, index: usize) -> Option<Vec<T>>;
+}
+
+/// A production ruleset that gives each atom a list of productions and
+/// picks the one at the atom's index modulo the length of the list.
+///
+/// Two productions alternate between even and odd positions, three repeat
+/// every third position, and so on, which varies a pattern across the state
+/// deterministically, without the parameters of a parametric system.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LSystem, ModRules, show};
+///
+/// let mut rules = ModRules::new();
+/// rules.set_str('L', &["L+", "L-"]);
+/// let mut system = LSystem::new(rules, "LLLL".chars().collect());
+///
+/// assert_eq!("L+L-L+L-", show(&system.next().unwrap()));
+/// // the signs now count as positions too
+/// assert_eq!("L++L+-L++L+-", show(&system.next().unwrap()));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ModRules<T: RuleKey> {
+    productions: RuleMap<T, Vec<Vec<T>>>,
+}
+
+impl<T> ModRules<T> where T: RuleKey {
+    /// Create a new, empty ruleset.
+    pub fn new() -> ModRules<T> {
+        ModRules {
+            productions: RuleMap::new(),
+        }
+    }
+
+    /// Set an atom to produce the production at its index modulo the
+    /// number of productions, returning the productions it replaces, if
+    /// any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is empty.
+    pub fn set(&mut self, k: T, v: Vec<Vec<T>>) -> Option<Vec<Vec<T>>> {
+        assert!(!v.is_empty(), "an atom needs at least one production");
+        self.productions.insert(k, v)
+    }
+
+    /// Get the productions for an atom, if it has any.
+    pub fn get(&self, k: &T) -> Option<&Vec<Vec<T>>> {
+        self.productions.get(k)
+    }
+
+    /// The number of atoms that have a rule.
+    pub fn len(&self) -> usize {
+        self.productions.len()
+    }
+
+    /// Check whether the ruleset has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.productions.is_empty()
+    }
+}
+
+impl ModRules<char> {
+    /// Set a char to produce the chars of the string at its index modulo
+    /// the number of strings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is empty.
+    pub fn set_str(&mut self, k: char, v: &[&str]) -> Option<Vec<Vec<char>>> {
+        self.set(k, v.iter().map(|s| s.chars().collect()).collect())
+    }
+}
+
+impl<T> Default for ModRules<T> where T: RuleKey {
+    fn default() -> ModRules<T> {
+        ModRules::new()
+    }
+}
+
+impl<T> IndexedRules<T> for ModRules<T> where T: Clone + RuleKey {
+    fn map_indexed(&self, atom: &T, index: usize) -> Option<Vec<T>> {
+        self.productions.get(atom).map(|v| v[index % v.len()].clone())
+    }
+}
+
+impl<T> LRules<T> for ModRules<T> where T: Clone + RuleKey {
+    /// Map an atom as though it were at index zero.
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.map_indexed(input, 0)
+    }
+
+    fn map_at(&self, state: &[T], index: usize) -> Option<Vec<T>> {
+        self.map_indexed(&state[index], index)
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.productions.contains_key(&state[index])
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        Some(self.productions.keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {show, LSystem};
+
+    #[test]
+    fn test_alternate_by_parity() {
+        let mut rules = ModRules::new();
+        rules.set_str('A', &["AB", "BA"]);
+        rules.set_str('B', &["B"]);
+        assert_eq!(Some(vec!['A', 'B']), rules.map_indexed(&'A', 4));
+        assert_eq!(Some(vec!['B', 'A']), rules.map_indexed(&'A', 7));
+
+        let mut system = LSystem::new(rules, vec!['A', 'A', 'B', 'A']);
+        assert_eq!("ABBABBA", show(&system.next().unwrap()));
+        assert_eq!("ABBBBABBAB", show(&system.next().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one production")]
+    fn test_no_productions() {
+        ModRules::new().set('A', Vec::new());
+    }
+}
@@ -0,0 +1,176 @@
+//! Watching the rules fire, for profiling and debugging grammars.
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+use {LRules, LSystem};
+
+/// A callback given each atom that is rewritten and its production.
+type Callback<T> = Box<dyn FnMut(&T, &[T]) + Send>;
+
+/// The observer of an `LSystem`, if one is set.
+///
+/// Closures cannot be cloned or serialized, so a clone of an `LSystem` and
+/// a deserialized one start out without an observer.  It must be `Send`,
+/// so that an `LSystem` can still be sent to another thread.
+pub struct Observer<T>(Option<Callback<T>>);
+
+impl<T> Observer<T> {
+    /// whether an observer is set.
+    pub fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// tell the observer, if there is one, that `atom` was rewritten to
+    /// `production`.
+    #[inline]
+    pub fn rewrote(&mut self, atom: &T, production: &[T]) {
+        if let Some(ref mut f) = self.0 {
+            f(atom, production);
+        }
+    }
+}
+
+impl<T> Default for Observer<T> {
+    fn default() -> Observer<T> {
+        Observer(None)
+    }
+}
+
+impl<T> Clone for Observer<T> {
+    fn clone(&self) -> Observer<T> {
+        Observer(None)
+    }
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Clone {
+    /// call `f` with each atom the L-System rewrites and the production it
+    /// is rewritten to, replacing any observer set before.
+    ///
+    /// It sees every generation the system builds, one atom at a time,
+    /// whichever method builds it, which makes it a simple way to count
+    /// which rules fire most in a complex grammar.  It is not called for the
+    /// generations that `find_cycle`, `growth_profile` and
+    /// `turtle::render_frames` build and then throw away.  Without an observer,
+    /// building a generation costs a check of an `Option` and nothing more.
+    /// With one, `advance` and `next` look up each production on its own
+    /// rather than expanding the state in one pass.
+    ///
+    /// The observer is not copied by `clone`, nor serialized.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use std::sync::{Arc, Mutex};
+    /// use lsystem::{LSystem, MapRules};
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let mut system = LSystem::new(rules, vec!['A']);
+    ///
+    /// let fired = Arc::new(Mutex::new(HashMap::new()));
+    /// let counts = fired.clone();
+    /// system.set_observer(Box::new(move |atom: &char, _: &[char]| {
+    ///     *counts.lock().unwrap().entry(*atom).or_insert(0) += 1;
+    /// }));
+    /// system.nth(4);
+    ///
+    /// // five generations rewrote 1, 2, 3, 5 and 8 atoms
+    /// let fired = fired.lock().unwrap();
+    /// assert_eq!(12, fired[&'A']);
+    /// assert_eq!(7, fired[&'B']);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn set_observer(&mut self, f: Box<dyn FnMut(&T, &[T]) + Send>) {
+        self.observer = Observer(Some(f));
+    }
+
+    /// remove the observer, if one is set.
+    pub fn clear_observer(&mut self) {
+        self.observer = Observer::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use {LSystem, MapRules, Progress};
+    #[cfg(feature = "std")]
+    use turtle::{render_frames, TurtleConfig};
+
+    type Log = Arc<Mutex<Vec<(char, Vec<char>)>>>;
+
+    fn observed() -> (LSystem<char, MapRules<char>>, Log) {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AB");
+        rules.set_str('B', "A");
+        let mut system = LSystem::new(rules, vec!['A', 'B']);
+        let log = Log::default();
+        let sink = log.clone();
+        system.set_observer(Box::new(move |atom: &char, production: &[char]| {
+            sink.lock().unwrap().push((*atom, production.to_vec()));
+        }));
+        (system, log)
+    }
+
+    #[test]
+    fn test_every_path_observed() {
+        let expected = vec![('A', vec!['A', 'B']), ('B', vec!['A'])];
+
+        let (mut system, log) = observed();
+        system.advance();
+        assert_eq!(expected, *log.lock().unwrap());
+
+        let (mut system, log) = observed();
+        system.next_partial(2);
+        assert_eq!(expected, *log.lock().unwrap());
+
+        let (mut system, log) = observed();
+        assert_eq!(Progress::InProgress, system.next_budgeted(1));
+        system.next_budgeted(1);
+        assert_eq!(expected, *log.lock().unwrap());
+
+        let (mut system, log) = observed();
+        system.next_with_spans();
+        assert_eq!(expected, *log.lock().unwrap());
+
+        let (mut system, log) = observed();
+        system.next_where(|&c| c == 'B');
+        assert_eq!(vec![('B', vec!['A'])], *log.lock().unwrap());
+    }
+
+    #[test]
+    fn test_clone_and_clear() {
+        let (mut system, log) = observed();
+        let mut copy = system.clone();
+        copy.advance();
+        assert!(log.lock().unwrap().is_empty());
+
+        system.clear_observer();
+        system.advance();
+        assert!(log.lock().unwrap().is_empty());
+        assert_eq!(copy.current_state(), system.current_state());
+    }
+
+    #[test]
+    fn test_system_is_send() {
+        fn assert_send<S: Send>(_: &S) {}
+        let (system, _) = observed();
+        assert_send(&system);
+    }
+
+    #[test]
+    fn test_probes_not_observed() {
+        let (mut system, log) = observed();
+        assert_eq!(vec![2, 3, 5], system.growth_profile(2));
+        assert_eq!(None, system.find_cycle(10));
+        #[cfg(feature = "std")]
+        render_frames(&mut system, 2, &TurtleConfig::default());
+        assert!(log.lock().unwrap().is_empty());
+
+        // the observer is still there afterwards
+        system.advance();
+        assert_eq!(2, log.lock().unwrap().len());
+    }
+}
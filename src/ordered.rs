@@ -0,0 +1,144 @@
+//! A production ruleset with a deterministic iteration order.
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use LRules;
+
+/// A production ruleset that maps an atom to an atom string using an ordered
+/// lookup table.
+///
+/// It expands states exactly as `MapRules` does, but iterates over its rules
+/// in ascending order of their atoms, which keeps output such as rule
+/// listings and serialized rule tables stable between runs.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LRules, OrderedMapRules};
+///
+/// let mut rules = OrderedMapRules::new();
+/// rules.set_str('B', "A");
+/// rules.set_str('A', "AB");
+///
+/// let keys: Vec<char> = rules.iter().map(|(k, _)| *k).collect();
+/// assert_eq!(vec!['A', 'B'], keys);
+/// assert_eq!(Some(vec!['A', 'B']), rules.map(&'A'));
+/// ```
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OrderedMapRules<T: Ord> {
+    productions: BTreeMap<T, Vec<T>>,
+}
+
+impl<T> OrderedMapRules<T> where T: Ord {
+    /// Create a new, empty ruleset.
+    pub fn new() -> OrderedMapRules<T> {
+        OrderedMapRules {
+            productions: BTreeMap::new(),
+        }
+    }
+
+    /// Set an atom to produce a vector
+    pub fn set(&mut self, k: T, v: Vec<T>) -> Option<Vec<T>> {
+        self.productions.insert(k, v)
+    }
+
+    /// Remove the rule for an atom, returning its production if it had one.
+    pub fn remove(&mut self, k: &T) -> Option<Vec<T>> {
+        self.productions.remove(k)
+    }
+
+    /// Check whether an atom has a rule.
+    pub fn contains(&self, k: &T) -> bool {
+        self.productions.contains_key(k)
+    }
+
+    /// Borrow the production for an atom, if it has one.
+    pub fn get(&self, k: &T) -> Option<&Vec<T>> {
+        self.productions.get(k)
+    }
+
+    /// Iterate over the rules as `(atom, production)` pairs, in ascending
+    /// order of atom.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &Vec<T>)> {
+        self.productions.iter()
+    }
+
+    /// The number of rules.
+    pub fn len(&self) -> usize {
+        self.productions.len()
+    }
+
+    /// Check whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.productions.is_empty()
+    }
+}
+
+impl OrderedMapRules<char> {
+    /// Set an atom to produce the Vec<char> corresponding to a string
+    pub fn set_str(&mut self, k: char, v: &str) -> Option<Vec<char>> {
+        self.set(k, v.chars().collect())
+    }
+}
+
+impl<T> Default for OrderedMapRules<T> where T: Ord {
+    fn default() -> OrderedMapRules<T> {
+        OrderedMapRules::new()
+    }
+}
+
+impl<T> LRules<T> for OrderedMapRules<T> where T: Clone + Ord {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.productions.get(input).cloned()
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.productions.contains_key(&state[index])
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        Some(self.productions.keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    #[test]
+    fn test_matches_map_rules() {
+        let mut ordered = OrderedMapRules::new();
+        let mut unordered = MapRules::new();
+        for &(k, v) in [('1', "11"), ('0', "1[0]0")].iter() {
+            ordered.set_str(k, v);
+            unordered.set_str(k, v);
+        }
+        let a = LSystem::new(ordered, vec!['0']);
+        let b = LSystem::new(unordered, vec!['0']);
+        for (x, y) in a.zip(b).take(8) {
+            assert_eq!(x, y);
+        }
+    }
+
+    #[test]
+    fn test_ordered_iteration() {
+        let mut rules = OrderedMapRules::new();
+        for k in [5, 3, 9, 1, 7].iter() {
+            rules.set(*k, vec![*k, *k]);
+        }
+        rules.remove(&9);
+        let keys: Vec<i32> = rules.iter().map(|(k, _)| *k).collect();
+        assert_eq!(vec![1, 3, 5, 7], keys);
+        assert_eq!(4, rules.len());
+    }
+}
@@ -0,0 +1,189 @@
+//! Parametric L-systems, where each atom carries a list of numeric
+//! parameters that productions can read and compute successors from.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use LRules;
+
+/// An atom made up of a symbol and a list of numeric parameters, written
+/// `A(x, y)` in the literature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parametric<S> {
+    pub symbol: S,
+    pub params: Vec<f64>,
+}
+
+impl<S> Parametric<S> {
+    /// Create a new parametric atom.
+    pub fn new(symbol: S, params: Vec<f64>) -> Parametric<S> {
+        Parametric {
+            symbol,
+            params,
+        }
+    }
+}
+
+impl<S> fmt::Display for Parametric<S> where S: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol)?;
+        if !self.params.is_empty() {
+            write!(f, "(")?;
+            for (i, p) in self.params.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", p)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of production rules for a parametric L-system.  Rules are selected
+/// by symbol, and compute the successor atoms from the parameters of the atom
+/// being rewritten.
+pub trait ParametricRules<S> {
+    /// perform a mapping of one parametric atom to a string.  It returns
+    /// `None` if the symbol has no production, or if the production does not
+    /// apply to these parameters.
+    fn map_params(&self, symbol: &S, params: &[f64]) -> Option<Vec<Parametric<S>>>;
+}
+
+type Production<S> = Box<dyn Fn(&[f64]) -> Option<Vec<Parametric<S>>>>;
+
+/// A production ruleset that maps each symbol to a closure over the atom's
+/// parameters.  A closure may return `None` to leave the atom unchanged, which
+/// is how conditional productions such as `A(x) : x > 1 -> ...` are written.
+///
+/// `ParametricMapRules<S>` implements `LRules<Parametric<S>>`, so it can drive
+/// an `LSystem` directly.
+///
+/// # Examples
+///
+/// The production `A(x) -> A(x*0.5) B(x+1)`:
+///
+/// ```
+/// use lsystem::{LSystem, Parametric, ParametricMapRules};
+///
+/// let mut rules = ParametricMapRules::new();
+/// rules.set('A', |p: &[f64]| Some(vec![
+///     Parametric::new('A', vec![p[0] * 0.5]),
+///     Parametric::new('B', vec![p[0] + 1.0]),
+/// ]));
+/// let mut system = LSystem::new(rules, vec![Parametric::new('A', vec![2.0])]);
+///
+/// let out = system.next().unwrap();
+/// assert_eq!(vec![
+///     Parametric::new('A', vec![1.0]),
+///     Parametric::new('B', vec![3.0]),
+/// ], out);
+/// ```
+pub struct ParametricMapRules<S: Hash + Eq> {
+    productions: HashMap<S, Production<S>>,
+}
+
+impl<S> ParametricMapRules<S> where S: Hash + Eq {
+    /// Create a new, empty ruleset.
+    pub fn new() -> ParametricMapRules<S> {
+        ParametricMapRules {
+            productions: HashMap::new(),
+        }
+    }
+
+    /// Set a symbol to produce the atoms computed by `f` from its parameters.
+    pub fn set<F>(&mut self, k: S, f: F) where F: Fn(&[f64]) -> Option<Vec<Parametric<S>>> + 'static {
+        self.productions.insert(k, Box::new(f));
+    }
+}
+
+impl<S> Default for ParametricMapRules<S> where S: Hash + Eq {
+    fn default() -> ParametricMapRules<S> {
+        ParametricMapRules::new()
+    }
+}
+
+impl<S> ParametricRules<S> for ParametricMapRules<S> where S: Hash + Eq {
+    fn map_params(&self, symbol: &S, params: &[f64]) -> Option<Vec<Parametric<S>>> {
+        self.productions.get(symbol).and_then(|f| f(params))
+    }
+}
+
+impl<S> LRules<Parametric<S>> for ParametricMapRules<S> where S: Hash + Eq {
+    fn map(&self, input: &Parametric<S>) -> Option<Vec<Parametric<S>>> {
+        self.map_params(&input.symbol, &input.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LSystem;
+
+    fn p(symbol: char, params: &[f64]) -> Parametric<char> {
+        Parametric::new(symbol, params.to_vec())
+    }
+
+    fn show(v: &[Parametric<char>]) -> String {
+        v.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_anabaena() {
+        // Anabaena catenula, with the cell polarity carried as a parameter:
+        // 0 for left, 1 for right.
+        let mut rules = ParametricMapRules::new();
+        rules.set('a', |x: &[f64]| Some(if x[0] == 1.0 {
+            vec![p('a', &[0.0]), p('b', &[1.0])]
+        } else {
+            vec![p('b', &[0.0]), p('a', &[1.0])]
+        }));
+        rules.set('b', |x: &[f64]| Some(vec![p('a', x)]));
+        let mut system = LSystem::new(rules, vec![p('a', &[1.0])]);
+
+        assert_eq!("a(0)b(1)", show(&system.next().unwrap()));
+        assert_eq!("b(0)a(1)a(1)", show(&system.next().unwrap()));
+        assert_eq!("a(0)a(0)b(1)a(0)b(1)", show(&system.next().unwrap()));
+        assert_eq!("b(0)a(1)b(0)a(1)a(1)b(0)a(1)a(1)", show(&system.next().unwrap()));
+    }
+
+    #[test]
+    fn test_conditional_productions() {
+        // ABOP equation 1.8
+        let mut rules = ParametricMapRules::new();
+        rules.set('A', |x: &[f64]| Some(if x[1] <= 3.0 {
+            vec![p('A', &[x[0] * 2.0, x[0] + x[1]])]
+        } else {
+            vec![p('B', &[x[0]]), p('A', &[x[0] / x[1], 0.0])]
+        }));
+        rules.set('B', |x: &[f64]| Some(if x[0] < 1.0 {
+            vec![p('C', &[])]
+        } else {
+            vec![p('B', &[x[0] - 1.0])]
+        }));
+        let axiom = vec![p('B', &[2.0]), p('A', &[4.0, 4.0])];
+        let mut system = LSystem::new(rules, axiom);
+
+        assert_eq!("B(1)B(4)A(1,0)", show(&system.next().unwrap()));
+        assert_eq!("B(0)B(3)A(2,1)", show(&system.next().unwrap()));
+        assert_eq!("CB(2)A(4,3)", show(&system.next().unwrap()));
+        assert_eq!("CB(1)A(8,7)", show(&system.next().unwrap()));
+    }
+
+    #[test]
+    fn test_guard_leaves_atom() {
+        let mut rules = ParametricMapRules::new();
+        rules.set('A', |x: &[f64]| if x[0] > 1.0 {
+            Some(vec![p('A', &[x[0] - 1.0])])
+        } else {
+            None
+        });
+        let mut system = LSystem::new(rules, vec![p('A', &[2.5])]);
+
+        assert_eq!(vec![p('A', &[1.5])], system.next().unwrap());
+        assert_eq!(vec![p('A', &[0.5])], system.next().unwrap());
+        assert_eq!(None, system.next());
+    }
+}
@@ -0,0 +1,303 @@
+//! Parsing of L-system rules from text.
+
+use std::error::Error;
+use std::fmt;
+
+use turtle::TurtleConfig;
+use {LSystem, MapRules};
+
+/// The ways a line of rule text can be malformed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// the line has no `->` separating predecessor from successor
+    MissingArrow,
+    /// the left-hand side is not exactly one symbol
+    InvalidPredecessor(String),
+    /// the symbol already has a rule on an earlier line
+    DuplicateRule(char),
+    /// a header line sets a key that is not understood
+    UnknownKey(String),
+    /// a header key is set on an earlier line too
+    DuplicateKey(String),
+    /// a header value could not be read, such as an angle that is not a
+    /// number
+    InvalidValue(String),
+    /// no header line sets the axiom
+    MissingAxiom,
+}
+
+/// An error encountered while parsing rule text, with the 1-based number of
+/// the offending line, or 0 if the error is not on any one line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::MissingArrow =>
+                write!(f, "line {}: expected a rule of the form `A -> AB`", self.line),
+            ParseErrorKind::InvalidPredecessor(ref s) =>
+                write!(f, "line {}: predecessor `{}` must be a single symbol", self.line, s),
+            ParseErrorKind::DuplicateRule(c) =>
+                write!(f, "line {}: duplicate rule for `{}`", self.line, c),
+            ParseErrorKind::UnknownKey(ref k) =>
+                write!(f, "line {}: unknown key `{}`; expected `angle`, `axiom` or `step`", self.line, k),
+            ParseErrorKind::DuplicateKey(ref k) =>
+                write!(f, "line {}: duplicate key `{}`", self.line, k),
+            ParseErrorKind::InvalidValue(ref v) =>
+                write!(f, "line {}: invalid value `{}`", self.line, v),
+            ParseErrorKind::MissingAxiom =>
+                write!(f, "no axiom given; expected a line of the form `axiom=F`"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl MapRules<char> {
+    /// Parse a ruleset from text with one rule per line, written as
+    /// `A -> AB`.  Blank lines and lines starting with `#` are ignored, as is
+    /// any whitespace within a successor.  An empty successor is allowed and
+    /// makes the symbol disappear.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` naming the line if it lacks `->`, if its
+    /// predecessor is not a single symbol, or if that symbol already has a
+    /// rule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lsystem::{LRules, MapRules};
+    ///
+    /// let rules = MapRules::from_rules_str("
+    ///     ## algae
+    ///     A -> AB
+    ///     B -> A
+    /// ").unwrap();
+    /// assert_eq!(Some(vec!['A', 'B']), rules.map(&'A'));
+    ///
+    /// let err = MapRules::from_rules_str("A -> AB\nB = A").unwrap_err();
+    /// assert_eq!(2, err.line);
+    /// ```
+    pub fn from_rules_str(s: &str) -> Result<MapRules<char>, ParseError> {
+        let mut rules = MapRules::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let arrow = line.find("->").ok_or(ParseError { line: i + 1, kind: ParseErrorKind::MissingArrow })?;
+            set_rule(&mut rules, line, arrow).map_err(|kind| ParseError { line: i + 1, kind })?;
+        }
+        Ok(rules)
+    }
+}
+
+/// Add the rule on a line whose `->` is at byte `arrow`.
+fn set_rule(rules: &mut MapRules<char>, line: &str, arrow: usize) -> Result<(), ParseErrorKind> {
+    let lhs = line[..arrow].trim();
+    let mut chars = lhs.chars();
+    let k = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => return Err(ParseErrorKind::InvalidPredecessor(lhs.to_string())),
+    };
+    let v = line[arrow + 2..].chars().filter(|c| !c.is_whitespace()).collect();
+    if rules.set(k, v).is_some() {
+        return Err(ParseErrorKind::DuplicateRule(k));
+    }
+    Ok(())
+}
+
+/// An L-system read from a text description, together with how to draw it.
+///
+/// The format is a small subset of the ones the cpfg and Houdini L-system
+/// tools read, enough to carry over most published examples.  A header of
+/// `key=value` lines comes first, followed by the productions, one per line
+/// and written as in `MapRules::from_rules_str`.  The header keys are:
+///
+/// | Key     | Value                                               |
+/// |---------|-----------------------------------------------------|
+/// | `axiom` | the starting state, required                        |
+/// | `angle` | the turn angle in degrees, 90 if not given          |
+/// | `step`  | the length of a forward move, 1 if not given        |
+///
+/// Blank lines and lines starting with `#` are ignored throughout, as is
+/// whitespace around keys and values and within the axiom.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::SystemSpec;
+///
+/// let spec = SystemSpec::parse("
+///     ## Koch curve
+///     angle=60
+///     axiom=F--F--F
+///     F -> F+F--F+F
+/// ").unwrap();
+/// assert_eq!(60f64.to_radians(), spec.config.angle);
+///
+/// let mut system = spec.into_system();
+/// assert_eq!(3 * 4, system.next().unwrap().iter().filter(|&&c| c == 'F').count());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SystemSpec {
+    pub axiom: Vec<char>,
+    pub rules: MapRules<char>,
+    /// the turtle configuration given by `angle` and `step`, otherwise the
+    /// default
+    pub config: TurtleConfig,
+}
+
+impl SystemSpec {
+    /// Parse a description in the format described above.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ParseError` naming the line if a header line sets an
+    /// unknown key, sets a key twice or has a value that is not a number
+    /// where one is needed, if a header line comes after the productions, or
+    /// if a production is malformed as for `MapRules::from_rules_str`.
+    /// Returns `MissingAxiom`, on line 0, if there is no axiom.
+    ///
+    /// ```
+    /// use lsystem::{ParseErrorKind, SystemSpec};
+    ///
+    /// let err = SystemSpec::parse("axiom=F\niterations=4").unwrap_err();
+    /// assert_eq!(2, err.line);
+    /// assert_eq!(ParseErrorKind::UnknownKey("iterations".to_string()), err.kind);
+    /// ```
+    pub fn parse(s: &str) -> Result<SystemSpec, ParseError> {
+        let mut axiom = None;
+        let mut angle = None;
+        let mut step = None;
+        let mut rules = MapRules::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |kind| ParseError { line: i + 1, kind };
+            if let Some(arrow) = line.find("->") {
+                set_rule(&mut rules, line, arrow).map_err(error)?;
+                continue;
+            }
+            let eq = line.find('=').ok_or_else(|| error(ParseErrorKind::MissingArrow))?;
+            if !rules.is_empty() {
+                return Err(error(ParseErrorKind::MissingArrow));
+            }
+            let key = line[..eq].trim();
+            let value = line[eq + 1..].trim();
+            let is_set = match key {
+                "axiom" => axiom.replace(value.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>()).is_some(),
+                "angle" => angle.replace(parse_number(value).map_err(error)?).is_some(),
+                "step" => step.replace(parse_number(value).map_err(error)?).is_some(),
+                _ => return Err(error(ParseErrorKind::UnknownKey(key.to_string()))),
+            };
+            if is_set {
+                return Err(error(ParseErrorKind::DuplicateKey(key.to_string())));
+            }
+        }
+
+        let axiom = axiom.ok_or(ParseError { line: 0, kind: ParseErrorKind::MissingAxiom })?;
+        let mut config = TurtleConfig::default();
+        if let Some(degrees) = angle {
+            config.angle = f64::to_radians(degrees);
+        }
+        if let Some(step) = step {
+            config.step = step;
+        }
+        Ok(SystemSpec { axiom, rules, config })
+    }
+
+    /// Build an L-system from the axiom and rules, leaving the
+    /// configuration behind.
+    pub fn into_system(self) -> LSystem<char, MapRules<char>> {
+        LSystem::new(self.rules, self.axiom)
+    }
+}
+
+/// Read a finite number from a header value.
+fn parse_number(value: &str) -> Result<f64, ParseErrorKind> {
+    match value.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(ParseErrorKind::InvalidValue(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LRules;
+
+    #[test]
+    fn test_parse() {
+        let rules = MapRules::from_rules_str("\
+            # pythagoras tree\n\
+            \n\
+            1 -> 11\n\
+            0 -> 1 [0] 0\n\
+            X ->\n").unwrap();
+        assert_eq!(Some("11".chars().collect()), rules.map(&'1'));
+        assert_eq!(Some("1[0]0".chars().collect()), rules.map(&'0'));
+        assert_eq!(Some(vec![]), rules.map(&'X'));
+        assert_eq!(None, rules.map(&'['));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = MapRules::from_rules_str("A -> B\n\nA B").unwrap_err();
+        assert_eq!(ParseError { line: 3, kind: ParseErrorKind::MissingArrow }, err);
+
+        let err = MapRules::from_rules_str("AB -> B").unwrap_err();
+        assert_eq!(ParseErrorKind::InvalidPredecessor("AB".to_string()), err.kind);
+
+        let err = MapRules::from_rules_str(" -> B").unwrap_err();
+        assert_eq!(ParseErrorKind::InvalidPredecessor("".to_string()), err.kind);
+
+        let err = MapRules::from_rules_str("A -> B\n# c\nA -> C").unwrap_err();
+        assert_eq!(ParseError { line: 3, kind: ParseErrorKind::DuplicateRule('A') }, err);
+        assert_eq!("line 3: duplicate rule for `A`", err.to_string());
+    }
+
+    #[test]
+    fn test_spec() {
+        let spec = SystemSpec::parse("\
+            # fractal plant\n\
+            angle = 25\n\
+            step=0.5\n\
+            axiom = -X\n\
+            \n\
+            X -> F+[[X]-X]-F[-FX]+X\n\
+            F -> FF\n").unwrap();
+        assert_eq!(vec!['-', 'X'], spec.axiom);
+        assert_eq!(Some("FF".chars().collect()), spec.rules.map(&'F'));
+        assert_eq!(TurtleConfig { angle: 25f64.to_radians(), step: 0.5, ..TurtleConfig::default() }, spec.config);
+
+        let spec = SystemSpec::parse("axiom=A").unwrap();
+        assert_eq!(TurtleConfig::default(), spec.config);
+        assert!(spec.rules.is_empty());
+    }
+
+    #[test]
+    fn test_spec_errors() {
+        let err = |s| SystemSpec::parse(s).unwrap_err();
+        assert_eq!(ParseError { line: 2, kind: ParseErrorKind::UnknownKey("Angle".to_string()) },
+                   err("axiom=F\nAngle=90"));
+        assert_eq!(ParseError { line: 2, kind: ParseErrorKind::DuplicateKey("axiom".to_string()) },
+                   err("axiom=F\naxiom=G"));
+        assert_eq!(ParseError { line: 1, kind: ParseErrorKind::InvalidValue("ninety".to_string()) },
+                   err("angle=ninety\naxiom=F"));
+        assert_eq!(ParseError { line: 0, kind: ParseErrorKind::MissingAxiom }, err("F -> FF"));
+        // the header must come before the productions
+        assert_eq!(ParseError { line: 2, kind: ParseErrorKind::MissingArrow }, err("F -> FF\naxiom=F"));
+        assert_eq!(ParseErrorKind::DuplicateRule('F'), err("axiom=F\nF -> F\nF -> FF").kind);
+        assert_eq!("line 2: unknown key `Angle`; expected `angle`, `axiom` or `step`",
+                   err("axiom=F\nAngle=90").to_string());
+    }
+}
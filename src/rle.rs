@@ -0,0 +1,72 @@
+//! Run-length encoding of states, for inspecting or storing states with
+//! long runs of the same atom.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Encode a state as a list of atoms and how many times each repeats in a
+/// row.
+///
+/// This is a utility for looking at states and writing them out compactly;
+/// an `LSystem` always stores its state expanded.  The counts are never
+/// zero, and no two neighbouring runs have equal atoms.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{run_length_decode, run_length_encode};
+///
+/// let state: Vec<char> = "FFFF+FF".chars().collect();
+/// let runs = run_length_encode(&state);
+/// assert_eq!(vec![('F', 4), ('+', 1), ('F', 2)], runs);
+/// assert_eq!(state, run_length_decode(&runs));
+/// ```
+pub fn run_length_encode<T>(state: &[T]) -> Vec<(T, usize)> where T: PartialEq + Clone {
+    let mut runs: Vec<(T, usize)> = Vec::new();
+    for atom in state.iter() {
+        match runs.last_mut() {
+            Some(run) if run.0 == *atom => run.1 += 1,
+            _ => runs.push((atom.clone(), 1)),
+        }
+    }
+    runs
+}
+
+/// Expand runs of atoms, as given by `run_length_encode`, back into a
+/// state.  Runs with a count of zero are skipped.
+pub fn run_length_decode<T>(runs: &[(T, usize)]) -> Vec<T> where T: Clone {
+    let len = runs.iter().fold(0, |len: usize, run| len.saturating_add(run.1));
+    let mut state = Vec::with_capacity(len);
+    for &(ref atom, count) in runs.iter() {
+        state.extend((0..count).map(|_| atom.clone()));
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    #[test]
+    fn test_round_trip() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "FF");
+        rules.set_str('X', "F[X]-X");
+        let system = LSystem::new(rules, vec!['X']);
+        for state in system.take(6) {
+            let runs = run_length_encode(&state);
+            assert!(runs.windows(2).all(|w| w[0].0 != w[1].0));
+            assert_eq!(state.len(), runs.iter().map(|r| r.1).sum::<usize>());
+            assert_eq!(state, run_length_decode(&runs));
+        }
+    }
+
+    #[test]
+    fn test_edges() {
+        assert!(run_length_encode::<u8>(&[]).is_empty());
+        assert!(run_length_decode::<u8>(&[]).is_empty());
+        assert_eq!(vec![(7, 1)], run_length_encode(&[7]));
+        assert_eq!(vec![1, 2, 2], run_length_decode(&[(1, 1), (3, 0), (2, 2)]));
+    }
+}
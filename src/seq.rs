@@ -0,0 +1,113 @@
+//! Rewriting of multi-atom patterns, for passes such as simplification that
+//! work on sequences rather than single atoms.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A set of rules that replace sequences of atoms.
+///
+/// These are not `LRules`, which rewrite one atom at a time.  Instead,
+/// `apply` makes a single pass over a whole state, which makes them suited
+/// to pre- or post-processing a state, such as merging repeated moves.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{show, SeqRules};
+///
+/// let mut rules = SeqRules::new();
+/// rules.set_seq(vec!['F', 'F'], vec!['F']);
+/// rules.set_seq(vec!['+', '-'], vec![]);
+///
+/// let state: Vec<char> = "FFF+-F".chars().collect();
+/// assert_eq!("FFF", show(&rules.apply(&state)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeqRules<T: Hash + Eq> {
+    replacements: HashMap<Vec<T>, Vec<T>>,
+    longest: usize,
+}
+
+impl<T> SeqRules<T> where T: Hash + Eq + Clone {
+    /// Create a new, empty ruleset.
+    pub fn new() -> SeqRules<T> {
+        SeqRules {
+            replacements: HashMap::new(),
+            longest: 0,
+        }
+    }
+
+    /// Set a sequence of atoms to be replaced by another, returning the
+    /// replacement it had before, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is empty.
+    pub fn set_seq(&mut self, pattern: Vec<T>, replacement: Vec<T>) -> Option<Vec<T>> {
+        assert!(!pattern.is_empty(), "a pattern needs at least one atom");
+        self.longest = self.longest.max(pattern.len());
+        self.replacements.insert(pattern, replacement)
+    }
+
+    /// Rewrite a state in one pass from left to right.  At each position the
+    /// longest pattern that matches there is replaced, and scanning resumes
+    /// after it, so matches never overlap and replacements are not rescanned.
+    /// Atoms not covered by any match are copied through unchanged.
+    pub fn apply(&self, state: &[T]) -> Vec<T> {
+        let mut out = Vec::with_capacity(state.len());
+        let mut i = 0;
+        while i < state.len() {
+            let longest = self.longest.min(state.len() - i);
+            let matched = (1..longest + 1).rev()
+                .filter_map(|len| self.replacements.get(&state[i..i + len]).map(|r| (len, r)))
+                .next();
+            match matched {
+                Some((len, replacement)) => {
+                    out.extend(replacement.iter().cloned());
+                    i += len;
+                },
+                None => {
+                    out.push(state[i].clone());
+                    i += 1;
+                },
+            }
+        }
+        out
+    }
+}
+
+impl<T> Default for SeqRules<T> where T: Hash + Eq + Clone {
+    fn default() -> SeqRules<T> {
+        SeqRules::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_doubles() {
+        let mut rules = SeqRules::new();
+        rules.set_seq(vec!['F', 'F'], vec!['F']);
+        rules.set_seq(vec!['G', 'G'], vec!['G']);
+        let state: Vec<char> = "FFFFGGGF".chars().collect();
+        assert_eq!("FFGGF".chars().collect::<Vec<char>>(), rules.apply(&state));
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let mut rules = SeqRules::new();
+        rules.set_seq(vec![1, 2], vec![9]);
+        rules.set_seq(vec![1, 2, 3], vec![7, 7]);
+        rules.set_seq(vec![3], vec![0]);
+        assert_eq!(vec![7, 7, 9, 1, 0], rules.apply(&[1, 2, 3, 1, 2, 1, 3]));
+        assert_eq!(Vec::<i32>::new(), rules.apply(&[]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_pattern() {
+        SeqRules::new().set_seq(vec![], vec!['A']);
+    }
+}
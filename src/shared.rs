@@ -0,0 +1,309 @@
+//! An L-system whose state shares storage between repeated substructures.
+//!
+//! In a context-free, deterministic L-system every copy of an atom grows into
+//! the same string after the same number of generations.  `SharedSystem`
+//! takes advantage of this by keeping the state as a tree in which each
+//! `(atom, generations)` expansion is built once and referenced with an `Rc`
+//! everywhere it appears, in the manner of hash-consing.
+//!
+//! # Memory
+//!
+//! A flat `LSystem` holds every atom of its state, so its memory grows with
+//! the length of the state, which is usually exponential in the number of
+//! generations.  A `SharedSystem` holds one node for each distinct atom at
+//! each generation reached, each with one reference per atom of its
+//! production, so its memory grows roughly with the number of generations
+//! times the size of the rules.  Generation 80 of the algae system is tens of
+//! quadrillions of atoms long but takes fewer than two hundred nodes.
+//!
+//! The price is paid when reading the state: `get` walks down the tree, and
+//! `iter` keeps a stack as deep as the number of generations, so visiting
+//! every atom is slower than scanning a `Vec`.  Materializing the state with
+//! `to_vec` costs as much memory as a flat `LSystem` would.  Only `map` is
+//! used to expand atoms, so context sensitive rules are treated as context
+//! free, and the rules must give the same production for an atom every time,
+//! which rules out stochastic rules.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use LRules;
+
+/// A node in the tree of a `SharedSystem`'s state.
+#[derive(Debug)]
+enum Node<T> {
+    Leaf(T),
+    Branch {
+        children: Vec<Rc<Node<T>>>,
+        len: usize,
+    },
+}
+
+impl<T> Node<T> {
+    fn len(&self) -> usize {
+        match *self {
+            Node::Leaf(_) => 1,
+            Node::Branch { len, .. } => len,
+        }
+    }
+}
+
+/// An L-system whose state is stored as a tree of shared expansions, which
+/// lets it reach generations far too long to hold in memory as a flat
+/// vector.  See the module documentation for the tradeoffs.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{MapRules, SharedSystem};
+///
+/// let mut rules = MapRules::new();
+/// rules.set_str('A', "AB");
+/// rules.set_str('B', "A");
+/// let mut system = SharedSystem::new(rules, vec!['A']);
+///
+/// system.advance();
+/// system.advance();
+/// assert_eq!(vec!['A', 'B', 'A'], system.to_vec());
+///
+/// for _ in 0..78 {
+///     system.advance();
+/// }
+/// assert_eq!(80, system.generation());
+/// assert_eq!(61305790721611591, system.len());
+/// assert_eq!(Some(&'A'), system.get(61305790721611590));
+/// ```
+pub struct SharedSystem<T, P> where P: LRules<T>, T: Clone + Hash + Eq {
+    rules: P,
+    axiom: Vec<T>,
+    generation: usize,
+    roots: Vec<Rc<Node<T>>>,
+    /// the expansion of each atom seen so far, by number of generations
+    levels: Vec<HashMap<T, Rc<Node<T>>>>,
+}
+
+impl<T, P> SharedSystem<T, P> where P: LRules<T>, T: Clone + Hash + Eq {
+    /// Create a new shared L-system from rules and an axiom.
+    pub fn new(rules: P, axiom: Vec<T>) -> SharedSystem<T, P> {
+        let mut system = SharedSystem {
+            rules,
+            axiom: Vec::new(),
+            generation: 0,
+            roots: Vec::new(),
+            levels: vec![HashMap::new()],
+        };
+        system.roots = axiom.iter().map(|atom| system.expand(atom, 0)).collect();
+        system.axiom = axiom;
+        system
+    }
+
+    /// The number of generations produced since the L-System was created.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Expand the L-System to its next generation, returning whether any
+    /// atom was expanded.
+    pub fn advance(&mut self) -> bool {
+        let generation = self.generation + 1;
+        let axiom = self.axiom.clone();
+        let roots: Vec<Rc<Node<T>>> = axiom.iter().map(|atom| self.expand(atom, generation)).collect();
+        let expanded = roots.iter().zip(self.roots.iter()).any(|(a, b)| !Rc::ptr_eq(a, b));
+        if expanded {
+            self.roots = roots;
+            self.generation = generation;
+        } else {
+            self.levels.truncate(generation);
+        }
+        expanded
+    }
+
+    /// The number of atoms in the current state.  This saturates at
+    /// `usize::MAX` rather than overflowing.
+    pub fn len(&self) -> usize {
+        self.roots.iter().fold(0usize, |sum, node| sum.saturating_add(node.len()))
+    }
+
+    /// Check whether the current state has no atoms.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Borrow the atom at `index` in the current state, walking down the
+    /// tree to find it.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let mut index = index;
+        let mut nodes = &self.roots;
+        loop {
+            let mut next = None;
+            for node in nodes.iter() {
+                if index < node.len() {
+                    next = Some(node);
+                    break;
+                }
+                index -= node.len();
+            }
+            match **next? {
+                Node::Leaf(ref atom) => return Some(atom),
+                Node::Branch { ref children, .. } => nodes = children,
+            }
+        }
+    }
+
+    /// Iterate over the atoms of the current state in order.
+    pub fn iter(&self) -> SharedIter<'_, T> {
+        SharedIter {
+            stack: vec![(&self.roots, 0)],
+        }
+    }
+
+    /// Copy the current state out into a flat vector.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    /// The number of expansions remembered, one for each distinct atom at
+    /// each generation reached, which is what the memory used grows with.
+    pub fn node_count(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    /// The expansion of `atom` after `generations` generations, building
+    /// and remembering it if it is new.
+    fn expand(&mut self, atom: &T, generations: usize) -> Rc<Node<T>> {
+        if let Some(node) = self.levels.get(generations).and_then(|level| level.get(atom)) {
+            return node.clone();
+        }
+        let node = if generations == 0 {
+            Rc::new(Node::Leaf(atom.clone()))
+        } else {
+            match self.rules.map(atom) {
+                Some(production) => {
+                    let children: Vec<Rc<Node<T>>> = production.iter()
+                        .map(|child| self.expand(child, generations - 1))
+                        .collect();
+                    // if none of the children changed in the last
+                    // generation, neither did this expansion
+                    let unchanged = generations > 1 && production.iter().zip(children.iter())
+                        .all(|(child, node)| Rc::ptr_eq(node, &self.expand(child, generations - 2)));
+                    if unchanged {
+                        self.expand(atom, generations - 1)
+                    } else {
+                        let len = children.iter().fold(0usize, |sum, node| sum.saturating_add(node.len()));
+                        Rc::new(Node::Branch {
+                            children,
+                            len,
+                        })
+                    }
+                },
+                None => self.expand(atom, generations - 1),
+            }
+        };
+        while self.levels.len() <= generations {
+            self.levels.push(HashMap::new());
+        }
+        self.levels[generations].insert(atom.clone(), node.clone());
+        node
+    }
+}
+
+/// An iterator over the atoms of a `SharedSystem`'s state.
+pub struct SharedIter<'a, T: 'a> {
+    /// the lists of nodes being walked, with the position reached in each
+    stack: Vec<(&'a Vec<Rc<Node<T>>>, usize)>,
+}
+
+impl<'a, T> Iterator for SharedIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            let (nodes, i) = {
+                let top = self.stack.last_mut()?;
+                let position = top.1;
+                top.1 += 1;
+                (top.0, position)
+            };
+            match nodes.get(i).map(|node| &**node) {
+                Some(Node::Leaf(atom)) => return Some(atom),
+                Some(Node::Branch { children, .. }) => self.stack.push((children, 0)),
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    fn pythagoras() -> MapRules<char> {
+        let mut rules = MapRules::new();
+        rules.set_str('1', "11");
+        rules.set_str('0', "1[0]0");
+        rules
+    }
+
+    #[test]
+    fn test_matches_flat_system() {
+        let mut flat = LSystem::new(pythagoras(), vec!['0', '1']);
+        let mut shared = SharedSystem::new(pythagoras(), vec!['0', '1']);
+        for _ in 0..8 {
+            assert_eq!(flat.current_state(), &shared.to_vec()[..]);
+            assert_eq!(flat.current_state().len(), shared.len());
+            for (i, atom) in flat.current_state().iter().enumerate() {
+                assert_eq!(Some(atom), shared.get(i));
+            }
+            assert_eq!(None, shared.get(shared.len()));
+            assert!(flat.advance());
+            assert!(shared.advance());
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_lengths() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AB");
+        rules.set_str('B', "A");
+        let mut system = SharedSystem::new(rules, vec!['A']);
+        let (mut a, mut b) = (1usize, 1usize);
+        for _ in 0..85 {
+            assert_eq!(a, system.len());
+            assert!(system.advance());
+            let next = a + b;
+            b = a;
+            a = next;
+        }
+    }
+
+    #[test]
+    fn test_sharing() {
+        let mut system = SharedSystem::new(pythagoras(), vec!['0']);
+        for _ in 0..50 {
+            system.advance();
+        }
+        // 0, 1 and the brackets at every generation
+        assert!(system.node_count() <= 4 * 51, "{} nodes", system.node_count());
+        assert_eq!(Some(&'0'), system.get(system.len() - 1));
+    }
+
+    #[test]
+    fn test_terminates() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "BC");
+        let mut system = SharedSystem::new(rules, vec!['A', 'C']);
+        assert!(system.advance());
+        assert!(!system.advance());
+        assert_eq!(1, system.generation());
+        assert_eq!(vec!['B', 'C', 'C'], system.to_vec());
+
+        let mut system = SharedSystem::new(MapRules::<char>::new(), vec![]);
+        assert!(system.is_empty());
+        assert!(!system.advance());
+        assert_eq!(None, system.iter().next());
+    }
+}
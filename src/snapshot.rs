@@ -0,0 +1,109 @@
+//! Saving and restoring the progress of an L-system, for undo.
+
+#[cfg(not(feature = "std"))]
+use alloc::sync::Arc;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use budget::Budgeted;
+use {LRules, LSystem};
+
+/// The state and generation of an `LSystem` at some point, taken by
+/// `LSystem::snapshot`.
+///
+/// The state is shared behind an `Arc`, so cloning a snapshot is cheap no
+/// matter how long the state is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot<T> {
+    state: Arc<Vec<T>>,
+    generation: usize,
+}
+
+impl<T> Snapshot<T> {
+    /// The state that was current when the snapshot was taken.
+    pub fn state(&self) -> &[T] {
+        &self.state
+    }
+
+    /// The generation that was current when the snapshot was taken.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Clone {
+    /// save the current state and generation, to go back to later with
+    /// `restore`.  This copies the state once; the snapshot can then be
+    /// cloned cheaply.
+    ///
+    /// ```
+    /// use lsystem::{LSystem, MapRules};
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// rules.set_str('B', "A");
+    /// let mut system = LSystem::new(rules, vec!['A']);
+    ///
+    /// system.next();
+    /// let undo = system.snapshot();
+    /// system.next();
+    /// system.next();
+    ///
+    /// system.restore(undo);
+    /// assert_eq!(1, system.generation());
+    /// assert_eq!(Some(vec!['A', 'B', 'A']), system.next());
+    /// ```
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot {
+            state: Arc::new(self.state.clone()),
+            generation: self.generation,
+        }
+    }
+
+    /// go back to the state and generation saved in a snapshot.  The rules
+    /// and axiom are kept as they are, and any generation that
+    /// `next_partial` or `next_budgeted` was part way through is abandoned.
+    ///
+    /// The state is moved out of the snapshot if nothing else shares it, and
+    /// copied otherwise.
+    pub fn restore(&mut self, snapshot: Snapshot<T>) {
+        self.state = Arc::try_unwrap(snapshot.state).unwrap_or_else(|shared| (*shared).clone());
+        self.generation = snapshot.generation;
+        self.cursor = 0;
+        self.pass_rewritten = false;
+        self.budgeted = Budgeted::default();
+        self.previous_len = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MapRules;
+
+    #[test]
+    fn test_undo_stack() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AB");
+        rules.set_str('B', "A");
+        let mut system = LSystem::new(rules, vec!['A']);
+
+        let mut history = Vec::new();
+        for _ in 0..4 {
+            history.push(system.snapshot());
+            system.advance();
+        }
+        let shared = history[1].clone();
+        while let Some(snapshot) = history.pop() {
+            system.restore(snapshot);
+            assert_eq!(history.len(), system.generation());
+        }
+        assert_eq!(&['A'], system.current_state());
+
+        system.restore(shared.clone());
+        assert_eq!(shared.state(), system.current_state());
+        assert_eq!(1, shared.generation());
+    }
+}
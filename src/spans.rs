@@ -0,0 +1,133 @@
+//! Tracing which atoms of a generation came from which atoms of the one
+//! before, for highlighting growth.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use budget::Budgeted;
+use {LRules, LSystem};
+
+/// The atoms of a new generation that one atom of the previous generation
+/// became, as returned by `LSystem::next_with_spans`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// the index of the atom in the previous state
+    pub source: usize,
+    /// the indices of the atoms it became in the new state, which is empty
+    /// if it was erased
+    pub range: Range<usize>,
+    /// whether the atom was expanded by a rule, rather than carried over
+    /// unchanged as a terminal
+    pub expanded: bool,
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Clone {
+    /// expand the L-System to its next generation, like `next()`, also
+    /// returning a span for each atom of the previous state that records
+    /// which atoms of the new state it became.
+    ///
+    /// The spans are in order, one per atom of the previous state, and
+    /// together cover the new state without gaps.  Expansion uses
+    /// `LRules::map_at`, so context sensitive rules behave as in `next()`.
+    /// Returns `None`, leaving the L-System as it was, if no atom expanded.
+    ///
+    /// ```
+    /// use lsystem::{LSystem, MapRules, Span};
+    ///
+    /// let mut rules = MapRules::new();
+    /// rules.set_str('A', "AB");
+    /// let mut system = LSystem::new(rules, vec!['B', 'A']);
+    ///
+    /// let (state, spans) = system.next_with_spans().unwrap();
+    /// assert_eq!(vec!['B', 'A', 'B'], state);
+    /// assert_eq!(Span { source: 0, range: 0..1, expanded: false }, spans[0]);
+    /// assert_eq!(Span { source: 1, range: 1..3, expanded: true }, spans[1]);
+    /// ```
+    pub fn next_with_spans(&mut self) -> Option<(Vec<T>, Vec<Span>)> {
+        self.start_generation();
+        let mut next = Vec::with_capacity(self.next_capacity());
+        let mut spans = Vec::with_capacity(self.state.len());
+        let mut expanded = false;
+        for i in 0..self.state.len() {
+            let start = next.len();
+            let atom_expanded = match self.rules.map_at(&self.state, i) {
+                Some(atoms) => {
+                    self.observer.rewrote(&self.state[i], &atoms);
+                    next.extend(atoms);
+                    true
+                },
+                None => {
+                    next.push(self.state[i].clone());
+                    false
+                },
+            };
+            expanded |= atom_expanded;
+            spans.push(Span {
+                source: i,
+                range: start..next.len(),
+                expanded: atom_expanded,
+            });
+        }
+        self.cursor = 0;
+        self.pass_rewritten = false;
+        self.budgeted = Budgeted::default();
+        if !expanded {
+            return None;
+        }
+        self.previous_len = Some(self.state.len());
+        self.state = next;
+        self.generation += 1;
+        Some((self.state.clone(), spans))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MapRules;
+
+    #[test]
+    fn test_spans_cover_state() {
+        let mut rules = MapRules::new();
+        rules.set_str('1', "11");
+        rules.set_str('0', "1[0]0");
+        rules.set_str('X', "");
+        let mut system = LSystem::new(rules.clone(), vec!['0', 'X']);
+        let mut plain = LSystem::new(rules, vec!['0', 'X']);
+
+        for _ in 0..4 {
+            let previous = system.current_state().to_vec();
+            let (state, spans) = system.next_with_spans().unwrap();
+            assert_eq!(plain.next().unwrap(), state);
+            assert_eq!(previous.len(), spans.len());
+
+            let mut end = 0;
+            for span in spans.iter() {
+                assert_eq!(end, span.range.start);
+                end = span.range.end;
+                let atom = previous[span.source];
+                assert_eq!(atom != '[' && atom != ']', span.expanded);
+                if !span.expanded {
+                    assert_eq!(&[atom], &state[span.range.clone()]);
+                }
+            }
+            assert_eq!(state.len(), end);
+        }
+    }
+
+    #[test]
+    fn test_erased_and_terminated() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "B");
+        rules.set_str('X', "");
+        let mut system = LSystem::new(rules, vec!['X', 'A']);
+        let (state, spans) = system.next_with_spans().unwrap();
+        assert_eq!(vec!['B'], state);
+        assert_eq!(Span { source: 0, range: 0..0, expanded: true }, spans[0]);
+        assert_eq!(Span { source: 1, range: 0..1, expanded: true }, spans[1]);
+
+        assert_eq!(None, system.next_with_spans());
+        assert_eq!(1, system.generation());
+    }
+}
@@ -0,0 +1,155 @@
+//! Production rules over static slices, for grammars built into the binary.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use {LRules, RuleKey, RuleMap};
+
+/// A production ruleset that maps an atom to a `&'static [T]` production,
+/// such as a constant array, rather than to a `Vec<T>` of its own.
+///
+/// Setting up the rules copies no productions, which saves allocating them
+/// all again for a large grammar that is already compiled into the
+/// binary.  A production is only copied when it is used to rewrite an
+/// atom.  For chars, `StaticStrRules` does the same with `&'static str`
+/// productions.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LSystem, StaticRules};
+///
+/// const A: &[u8] = &[0, 1];
+/// const B: &[u8] = &[0];
+///
+/// let rules = StaticRules::from_pairs(&[(0, A), (1, B)]);
+/// let mut system = LSystem::new(rules, vec![0]);
+/// assert_eq!(Some(vec![0, 1]), system.next());
+/// assert_eq!(Some(vec![0, 1, 0]), system.next());
+/// ```
+#[derive(Clone, Debug)]
+pub struct StaticRules<T: RuleKey + 'static> {
+    productions: RuleMap<T, &'static [T]>,
+}
+
+impl<T> StaticRules<T> where T: RuleKey + 'static {
+    /// Create a new, empty ruleset.
+    pub fn new() -> StaticRules<T> {
+        StaticRules {
+            productions: RuleMap::new(),
+        }
+    }
+
+    /// Create a ruleset from pairs of atoms and their productions.  Later
+    /// pairs overwrite earlier ones for the same atom.
+    pub fn from_pairs(pairs: &[(T, &'static [T])]) -> StaticRules<T> where T: Clone {
+        let mut rules = StaticRules::new();
+        for &(ref k, v) in pairs.iter() {
+            rules.set(k.clone(), v);
+        }
+        rules
+    }
+
+    /// Set an atom to produce a static slice, returning the production it
+    /// replaces, if any.
+    pub fn set(&mut self, k: T, v: &'static [T]) -> Option<&'static [T]> {
+        self.productions.insert(k, v)
+    }
+
+    /// Get the production for an atom, if it has one.
+    pub fn get(&self, k: &T) -> Option<&'static [T]> {
+        self.productions.get(k).cloned()
+    }
+
+    /// The number of atoms that have a rule.
+    pub fn len(&self) -> usize {
+        self.productions.len()
+    }
+
+    /// Check whether the ruleset has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.productions.is_empty()
+    }
+}
+
+impl<T> Default for StaticRules<T> where T: RuleKey + 'static {
+    fn default() -> StaticRules<T> {
+        StaticRules::new()
+    }
+}
+
+impl<T> LRules<T> for StaticRules<T> where T: Clone + RuleKey + 'static {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        self.get(input).map(|v| v.to_vec())
+    }
+
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        self.productions.contains_key(&state[index])
+    }
+
+    /// Expand a whole state in one pass, copying each production straight
+    /// from its slice into the new state.
+    fn map_all_into(&self, state: &[T], next: &mut Vec<T>) -> bool {
+        let start = next.len();
+        next.reserve(state.len());
+        let mut expanded = false;
+        for atom in state.iter() {
+            match self.productions.get(atom) {
+                Some(atoms) => {
+                    next.extend_from_slice(atoms);
+                    expanded = true;
+                },
+                None => next.push(atom.clone()),
+            }
+        }
+        if !expanded {
+            next.truncate(start);
+        }
+        expanded
+    }
+
+    /// Expand a whole state in one pass, moving each atom without a rule
+    /// into the new state.
+    fn map_all_drain(&self, state: &mut Vec<T>, next: &mut Vec<T>) -> bool {
+        let start = next.len();
+        next.reserve(state.len());
+        let mut expanded = false;
+        for atom in state.drain(..) {
+            match self.productions.get(&atom) {
+                Some(atoms) => {
+                    next.extend_from_slice(atoms);
+                    expanded = true;
+                },
+                None => next.push(atom),
+            }
+        }
+        if !expanded {
+            state.extend(next.drain(start..));
+        }
+        expanded
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        Some(self.productions.keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules};
+
+    static TREE: &[(char, &[char])] = &[('1', &['1', '1']), ('0', &['1', '[', '0', ']', '0'])];
+
+    #[test]
+    fn test_matches_map_rules() {
+        let rules = StaticRules::from_pairs(TREE);
+        assert_eq!(2, rules.len());
+        assert_eq!(Some(&['1', '1'][..]), rules.get(&'1'));
+
+        let map = MapRules::from_str_rules(&[('1', "11"), ('0', "1[0]0")]);
+        let a: Vec<Vec<char>> = LSystem::new(map, vec!['0']).take(5).collect();
+        let b: Vec<Vec<char>> = LSystem::new(rules, vec!['0']).take(5).collect();
+        assert_eq!(a, b);
+    }
+}
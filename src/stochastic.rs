@@ -0,0 +1,379 @@
+//! Stochastic production rules, where an atom may expand to one of several
+//! weighted alternatives.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use LRules;
+
+/// A source of randomness for stochastic rules.
+///
+/// Any generator from `rand` can be used as it is.  Implement this trait
+/// for another generator to reproduce the exact choices made by some other
+/// implementation, such as a reference model written in another language.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LRules, RuleRng, StochasticRules};
+///
+/// /// Cycles through a fixed list of numbers.
+/// struct Replay(Vec<f64>, usize);
+///
+/// impl RuleRng for Replay {
+///     fn gen_unit(&mut self) -> f64 {
+///         self.1 += 1;
+///         self.0[(self.1 - 1) % self.0.len()]
+///     }
+/// }
+///
+/// let mut rules = StochasticRules::with_rng(Replay(vec![0.9, 0.1], 0));
+/// rules.set_weighted('A', vec![(1.0, vec!['B']), (1.0, vec!['C'])]);
+/// assert_eq!(Some(vec!['C']), rules.map(&'A'));
+/// assert_eq!(Some(vec!['B']), rules.map(&'A'));
+/// ```
+pub trait RuleRng {
+    /// generate a number in the half-open range `[0, 1)`.
+    fn gen_unit(&mut self) -> f64;
+}
+
+impl<R> RuleRng for R where R: RngCore {
+    fn gen_unit(&mut self) -> f64 {
+        self.gen()
+    }
+}
+
+/// A production ruleset that maps an atom to one of several weighted
+/// alternatives, chosen at random each time the atom is rewritten.
+///
+/// `LRules::map` only borrows the ruleset immutably, so the random number
+/// generator lives in a `RefCell` and is advanced on every lookup.  This
+/// means a `StochasticRules` cannot be shared across threads, but it can be
+/// handed to an `LSystem` like any other ruleset.
+///
+/// The generator is a `StdRng` unless another `RuleRng` is given with
+/// `with_rng`.
+///
+/// # Examples
+///
+/// Seeding the generator makes the output reproducible:
+///
+/// ```
+/// use lsystem::{LSystem, StochasticRules};
+///
+/// let build = || {
+///     let mut rules = StochasticRules::with_seed(42);
+///     rules.set_weighted('F', vec![
+///         (1.0, "F[+F]F".chars().collect()),
+///         (1.0, "F[-F]F".chars().collect()),
+///     ]);
+///     LSystem::new(rules, vec!['F'])
+/// };
+///
+/// let a: Vec<Vec<char>> = build().take(3).collect();
+/// let b: Vec<Vec<char>> = build().take(3).collect();
+/// assert_eq!(a, b);
+/// ```
+///
+/// A single seed still makes each generation depend on how many numbers
+/// were drawn before it.  To make every generation depend only on the seed,
+/// the generation's number and the state it rewrites, enable
+/// `seed_per_generation`: the generator is then reseeded at the start of
+/// each generation, which `LSystem` announces through
+/// `LRules::start_generation`.  Reaching generation 7 by iterating, or by
+/// restoring a snapshot of generation 6 and stepping once, then gives the
+/// same result:
+///
+/// ```
+/// use lsystem::{LRules, LSystem, StochasticRules};
+///
+/// let mut rules = StochasticRules::new();
+/// rules.seed_per_generation(42);
+/// rules.set_weighted('F', vec![
+///     (1.0, "F[+F]".chars().collect()),
+///     (1.0, "F[-F]".chars().collect()),
+/// ]);
+/// let mut system = LSystem::new(rules, vec!['F']);
+///
+/// system.nth(5);
+/// let frame6 = system.snapshot();
+/// let frame7 = system.next().unwrap();
+///
+/// system.restore(frame6);
+/// system.rules().map(&'F'); // moves the generator on
+/// assert_eq!(frame7, system.next().unwrap());
+/// ```
+pub struct StochasticRules<T: Hash + Eq, R: RuleRng = StdRng> {
+    productions: HashMap<T, Vec<(f64, Vec<T>)>>,
+    rng: RefCell<R>,
+    /// the base seed and how to build a generator from it, when reseeding
+    /// at each generation
+    reseed: Option<Reseed<R>>,
+}
+
+/// A base seed, and the function that seeds a generator.
+type Reseed<R> = (u64, fn(u64) -> R);
+
+impl<T> StochasticRules<T> where T: Hash + Eq {
+    /// Create a new, empty ruleset with a randomly seeded generator.
+    pub fn new() -> StochasticRules<T> {
+        StochasticRules {
+            productions: HashMap::new(),
+            rng: RefCell::new(StdRng::from_entropy()),
+            reseed: None,
+        }
+    }
+
+    /// Create a new, empty ruleset whose generator is seeded with `seed`, so
+    /// that the same rules and axiom always produce the same generations.
+    pub fn with_seed(seed: u64) -> StochasticRules<T> {
+        StochasticRules {
+            productions: HashMap::new(),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            reseed: None,
+        }
+    }
+}
+
+impl<T, R> StochasticRules<T, R> where T: Hash + Eq, R: RuleRng {
+    /// Create a new, empty ruleset that draws its choices from `rng`.
+    pub fn with_rng(rng: R) -> StochasticRules<T, R> {
+        StochasticRules {
+            productions: HashMap::new(),
+            rng: RefCell::new(rng),
+            reseed: None,
+        }
+    }
+
+    /// Set an atom to produce one of several weighted alternatives.  The
+    /// weights are normalized so that they sum to 1.0.
+    ///
+    /// Each alternative is chosen with probability exactly proportional to
+    /// its weight, so alternatives of equal weight are equally likely
+    /// whatever order they are listed in.  The weights split `[0, 1)` into
+    /// consecutive half-open intervals, one per alternative in order, and the
+    /// alternative whose interval holds the generator's number is chosen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any weight is negative or not finite, or if the weights do
+    /// not add up to a positive total.
+    pub fn set_weighted(&mut self, k: T, v: Vec<(f64, Vec<T>)>) -> Option<Vec<(f64, Vec<T>)>> {
+        let mut total = 0.0;
+        for &(w, _) in v.iter() {
+            assert!(w.is_finite() && w >= 0.0, "invalid production weight: {}", w);
+            total += w;
+        }
+        assert!(total > 0.0, "production weights must have a positive sum");
+        let normalized = v.into_iter().map(|(w, p)| (w / total, p)).collect();
+        self.productions.insert(k, normalized)
+    }
+
+    /// Reseed the generator at the start of every generation from `seed`
+    /// and the number of the generation about to be produced, so that each
+    /// generation's choices are the same however it was reached.
+    ///
+    /// The reseeding happens when an `LSystem` starts a generation; looking
+    /// up productions with `map` directly draws from the generator as it
+    /// stands.
+    pub fn seed_per_generation(&mut self, seed: u64) where R: SeedableRng {
+        self.reseed = Some((seed, R::seed_from_u64));
+    }
+}
+
+/// Mix a base seed and a generation number into the seed for that
+/// generation, with the splitmix64 finalizer so that neighboring
+/// generations get unrelated seeds.
+fn generation_seed(seed: u64, generation: usize) -> u64 {
+    let mut z = seed.wrapping_add((generation as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl<T> Default for StochasticRules<T> where T: Hash + Eq {
+    fn default() -> StochasticRules<T> {
+        StochasticRules::new()
+    }
+}
+
+impl<T, R> LRules<T> for StochasticRules<T, R> where T: Clone + Hash + Eq, R: RuleRng {
+    fn map(&self, input: &T) -> Option<Vec<T>> {
+        let alternatives = self.productions.get(input)?;
+        let r = self.rng.borrow_mut().gen_unit();
+        let mut cumulative = 0.0;
+        for &(w, ref p) in alternatives.iter() {
+            cumulative += w;
+            if r < cumulative {
+                return Some(p.clone());
+            }
+        }
+        // rounding can leave the cumulative sum just shy of 1.0
+        alternatives.iter()
+            .rev()
+            .find(|&&(w, _)| w > 0.0)
+            .map(|(_, p)| p.clone())
+    }
+
+    /// Check for a production with a positive weight, without drawing a
+    /// random number.
+    fn rewrites_at(&self, state: &[T], index: usize) -> bool {
+        match self.productions.get(&state[index]) {
+            Some(alternatives) => alternatives.iter().any(|&(w, _)| w > 0.0),
+            None => false,
+        }
+    }
+
+    fn keys(&self) -> Option<Vec<&T>> {
+        Some(self.productions.keys().collect())
+    }
+
+    /// Reseed the generator, if `seed_per_generation` is enabled.
+    fn start_generation(&self, generation: usize) {
+        if let Some((seed, build)) = self.reseed {
+            *self.rng.borrow_mut() = build(generation_seed(seed, generation));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, Progress};
+
+    #[test]
+    fn test_seeded_reproducible() {
+        let build = |seed| {
+            let mut rules = StochasticRules::with_seed(seed);
+            rules.set_weighted('A', vec![
+                (1.0, vec!['A', 'B']),
+                (1.0, vec!['B', 'A']),
+            ]);
+            rules.set_weighted('B', vec![(1.0, vec!['A'])]);
+            LSystem::new(rules, vec!['A'])
+        };
+        let a: Vec<Vec<char>> = build(7).take(8).collect();
+        let b: Vec<Vec<char>> = build(7).take(8).collect();
+        assert_eq!(a, b);
+
+        // checking for productions does not draw from the generator
+        let mut system = build(7);
+        let mut c = Vec::new();
+        while c.len() < 8 && system.has_rewritable() {
+            c.extend(system.next());
+        }
+        assert_eq!(a, c);
+    }
+
+    #[test]
+    fn test_seed_per_generation() {
+        let build = || {
+            let mut rules = StochasticRules::new();
+            rules.seed_per_generation(7);
+            rules.set_weighted('A', vec![
+                (1.0, vec!['A', 'B']),
+                (1.0, vec!['B', 'A']),
+            ]);
+            rules.set_weighted('B', vec![(1.0, vec!['A']), (1.0, vec!['B', 'B'])]);
+            LSystem::new(rules, vec!['A'])
+        };
+        let expected: Vec<Vec<char>> = build().take(6).collect();
+
+        // a generation built in pieces draws the same numbers in the same
+        // order, as long as it is reseeded when it starts
+        let mut partial = build();
+        let mut budgeted = build();
+        for state in expected.iter() {
+            while partial.generation() < budgeted.generation() + 1 {
+                partial.next_partial(1);
+            }
+            assert_eq!(state.as_slice(), partial.current_state());
+            while budgeted.next_budgeted(1) == Progress::InProgress {}
+            assert_eq!(state.as_slice(), budgeted.current_state());
+        }
+
+        // the generator can be moved on between generations
+        let mut system = build();
+        for state in expected.iter() {
+            system.rules().map(&'A');
+            assert_eq!(Some(state.clone()), system.next());
+        }
+    }
+
+    #[test]
+    fn test_normalized_weights() {
+        let mut rules = StochasticRules::with_seed(0);
+        rules.set_weighted(0, vec![(3.0, vec![1]), (0.0, vec![2])]);
+        for _ in 0..100 {
+            assert_eq!(Some(vec![1]), rules.map(&0));
+        }
+        assert_eq!(None, rules.map(&1));
+    }
+
+    #[test]
+    fn test_chooses_among_alternatives() {
+        let mut rules = StochasticRules::with_seed(3);
+        rules.set_weighted('X', vec![(0.5, vec!['a']), (1.5, vec!['b'])]);
+        let mut seen_a = false;
+        let mut seen_b = false;
+        for _ in 0..200 {
+            match rules.map(&'X').unwrap()[0] {
+                'a' => seen_a = true,
+                'b' => seen_b = true,
+                _ => unreachable!(),
+            }
+        }
+        assert!(seen_a && seen_b);
+    }
+
+    /// Cycles through a fixed list of numbers.
+    struct Replay(Vec<f64>, usize);
+
+    impl RuleRng for Replay {
+        fn gen_unit(&mut self) -> f64 {
+            self.1 += 1;
+            self.0[(self.1 - 1) % self.0.len()]
+        }
+    }
+
+    #[test]
+    fn test_interval_boundaries() {
+        let draws = vec![0.0, 1.0 / 3.0 - 1e-12, 1.0 / 3.0, 2.0 / 3.0, 1.0 - 1e-12];
+        let mut rules = StochasticRules::with_rng(Replay(draws, 0));
+        rules.set_weighted('X', vec![(1.0, vec!['a']), (0.0, vec!['z']), (1.0, vec!['b']), (1.0, vec!['c'])]);
+        let chosen: Vec<char> = (0..5).map(|_| rules.map(&'X').unwrap()[0]).collect();
+        assert_eq!(vec!['a', 'a', 'b', 'c', 'c'], chosen);
+    }
+
+    #[test]
+    fn test_empirical_distribution() {
+        const SAMPLES: usize = 60_000;
+        let weights = [1.0, 1.0, 1.0, 2.0, 3.0];
+        let mut rules = StochasticRules::with_seed(11);
+        rules.set_weighted(0, weights.iter().enumerate().map(|(i, &w)| (w, vec![i])).collect());
+
+        let mut counts = [0usize; 5];
+        for _ in 0..SAMPLES {
+            counts[rules.map(&0).unwrap()[0]] += 1;
+        }
+        // the standard deviation of each proportion is at most 0.002 at this
+        // sample size, and an off-by-one in the search would shift a whole
+        // alternative's share
+        for (i, &count) in counts.iter().enumerate() {
+            let expected = weights[i] / 8.0;
+            let actual = count as f64 / SAMPLES as f64;
+            assert!((actual - expected).abs() < 0.005, "alternative {}: {} vs {}", i, actual, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_weight() {
+        let mut rules = StochasticRules::with_seed(0);
+        rules.set_weighted('X', vec![(-1.0, vec!['a'])]);
+    }
+}
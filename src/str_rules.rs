@@ -0,0 +1,249 @@
+//! Production rules and L-systems over `String` states, for compact
+//! char-based systems.
+
+use std::collections::HashMap;
+
+use LRules;
+
+/// A set of production rules over chars whose productions are string slices,
+/// so a state can be kept as a `String` rather than a `Vec<char>`.
+pub trait LStrRules {
+    /// perform a mapping of one char to a string.  It returns `None` if the
+    /// char should be considered terminal.
+    fn map_char(&self, input: char) -> Option<&str>;
+}
+
+/// A production ruleset that maps a char to a `String` using a lookup table.
+///
+/// Productions are stored as UTF-8 strings, which for ASCII-heavy systems
+/// take a quarter of the space of a `Vec<char>`.  `StrRules` drives a
+/// `StrSystem` to keep the state itself as a `String`, and also implements
+/// `LRules<char>` so it can be used with an ordinary `LSystem`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{StrRules, StrSystem};
+///
+/// let mut rules = StrRules::new();
+/// rules.set('A', "AB");
+/// rules.set('B', "A");
+/// let mut system = StrSystem::new(rules, "A");
+///
+/// assert_eq!(Some("AB".to_string()), system.next());
+/// assert_eq!(Some("ABA".to_string()), system.next());
+/// assert_eq!("ABA", system.current_state());
+/// ```
+#[derive(Debug, Default)]
+pub struct StrRules {
+    productions: HashMap<char, String>,
+}
+
+impl StrRules {
+    /// Create a new, empty ruleset.
+    pub fn new() -> StrRules {
+        StrRules {
+            productions: HashMap::new(),
+        }
+    }
+
+    /// Set a char to produce a string.
+    pub fn set(&mut self, k: char, v: &str) -> Option<String> {
+        self.productions.insert(k, v.to_string())
+    }
+}
+
+impl LStrRules for StrRules {
+    fn map_char(&self, input: char) -> Option<&str> {
+        self.productions.get(&input).map(|s| s.as_str())
+    }
+}
+
+impl LRules<char> for StrRules {
+    fn map(&self, input: &char) -> Option<Vec<char>> {
+        self.map_char(*input).map(|s| s.chars().collect())
+    }
+
+    fn keys(&self) -> Option<Vec<&char>> {
+        Some(self.productions.keys().collect())
+    }
+}
+
+/// A production ruleset that maps a char to a `&'static str`, such as a
+/// string literal, without copying it.
+///
+/// It is the `StaticRules` of `StrRules`: it drives a `StrSystem`, which
+/// copies each production straight from its literal into the new state, and
+/// implements `LRules<char>` for an ordinary `LSystem`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{StaticStrRules, StrSystem};
+///
+/// let rules = StaticStrRules::from_pairs(&[('A', "AB"), ('B', "A")]);
+/// let mut system = StrSystem::new(rules, "A");
+///
+/// assert_eq!(Some("AB".to_string()), system.next());
+/// assert_eq!(Some("ABA".to_string()), system.next());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StaticStrRules {
+    productions: HashMap<char, &'static str>,
+}
+
+impl StaticStrRules {
+    /// Create a new, empty ruleset.
+    pub fn new() -> StaticStrRules {
+        StaticStrRules {
+            productions: HashMap::new(),
+        }
+    }
+
+    /// Create a ruleset from pairs of chars and the strings they produce.
+    /// Later pairs overwrite earlier ones for the same char.
+    pub fn from_pairs(pairs: &[(char, &'static str)]) -> StaticStrRules {
+        StaticStrRules {
+            productions: pairs.iter().cloned().collect(),
+        }
+    }
+
+    /// Set a char to produce a string.
+    pub fn set(&mut self, k: char, v: &'static str) -> Option<&'static str> {
+        self.productions.insert(k, v)
+    }
+}
+
+impl LStrRules for StaticStrRules {
+    fn map_char(&self, input: char) -> Option<&str> {
+        self.productions.get(&input).cloned()
+    }
+}
+
+impl LRules<char> for StaticStrRules {
+    fn map(&self, input: &char) -> Option<Vec<char>> {
+        self.map_char(*input).map(|s| s.chars().collect())
+    }
+
+    fn keys(&self) -> Option<Vec<&char>> {
+        Some(self.productions.keys().collect())
+    }
+}
+
+/// An L-system whose state is a `String`.  It iterates just like `LSystem`,
+/// yielding each generation as a new `String`.
+pub struct StrSystem<P> where P: LStrRules {
+    rules: P,
+    pub axiom: String,
+    state: String,
+    generation: usize,
+}
+
+impl<P> StrSystem<P> where P: LStrRules {
+    /// create a new L-System from rules and an axiom
+    pub fn new(rules: P, axiom: &str) -> StrSystem<P> {
+        StrSystem {
+            rules,
+            axiom: axiom.to_string(),
+            state: axiom.to_string(),
+            generation: 0,
+        }
+    }
+
+    /// reset the L-System state back to its axiom
+    pub fn reset(&mut self) {
+        self.state = self.axiom.clone();
+        self.generation = 0;
+    }
+
+    /// the number of generations produced since the L-System was created or
+    /// last reset.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// borrow the current state of the L-System without cloning it.
+    pub fn current_state(&self) -> &str {
+        &self.state
+    }
+
+    /// expand the L-System to its next generation in place, returning
+    /// whether any char was expanded.
+    pub fn advance(&mut self) -> bool {
+        let mut next = String::with_capacity(self.state.len());
+        let mut expanded = false;
+        for c in self.state.chars() {
+            match self.rules.map_char(c) {
+                Some(s) => {
+                    next.push_str(s);
+                    expanded = true;
+                },
+                None => next.push(c),
+            }
+        }
+        self.state = next;
+        if expanded {
+            self.generation += 1;
+        }
+        expanded
+    }
+}
+
+impl<P> Iterator for StrSystem<P> where P: LStrRules {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.advance() {
+            Some(self.state.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {show, LSystem};
+
+    #[test]
+    fn test_matches_lsystem() {
+        let build = || {
+            let mut rules = StrRules::new();
+            rules.set('1', "11");
+            rules.set('0', "1[0]0");
+            rules
+        };
+        let strs = StrSystem::new(build(), "0");
+        let chars = LSystem::new(build(), vec!['0']);
+        for (a, b) in strs.zip(chars).take(6) {
+            assert_eq!(a, show(&b));
+        }
+    }
+
+    #[test]
+    fn test_static_str_rules() {
+        let rules = StaticStrRules::from_pairs(&[('1', "11"), ('0', "1[0]0")]);
+        assert_eq!(Some("11"), rules.map_char('1'));
+        assert_eq!(Some(vec!['1', '1']), rules.map(&'1'));
+
+        let strs = StrSystem::new(rules.clone(), "0");
+        let chars = LSystem::new(rules, vec!['0']);
+        for (a, b) in strs.zip(chars).take(6) {
+            assert_eq!(a, show(&b));
+        }
+    }
+
+    #[test]
+    fn test_terminates_and_resets() {
+        let mut rules = StrRules::new();
+        rules.set('a', "bé");
+        let mut system = StrSystem::new(rules, "aa");
+        assert_eq!(Some("bébé".to_string()), system.next());
+        assert_eq!(None, system.next());
+        assert_eq!(1, system.generation());
+        system.reset();
+        assert_eq!("aa", system.current_state());
+        assert_eq!(0, system.generation());
+    }
+}
@@ -0,0 +1,209 @@
+//! Measuring turtle output and fitting it to a canvas.
+
+use std::iter;
+
+use super::Segment;
+
+/// An axis-aligned rectangle in turtle coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// the corner with the smallest coordinates
+    pub min: (f64, f64),
+    /// the corner with the largest coordinates
+    pub max: (f64, f64),
+}
+
+impl Rect {
+    /// The extent of the rectangle along the x axis.
+    pub fn width(&self) -> f64 {
+        self.max.0 - self.min.0
+    }
+
+    /// The extent of the rectangle along the y axis.
+    pub fn height(&self) -> f64 {
+        self.max.1 - self.min.1
+    }
+
+    /// The point in the middle of the rectangle.
+    pub fn center(&self) -> (f64, f64) {
+        ((self.min.0 + self.max.0) / 2.0, (self.min.1 + self.max.1) / 2.0)
+    }
+}
+
+/// The smallest rectangle containing both ends of every segment, or `None`
+/// if there are no segments.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{bounds, interpret, Rect, TurtleConfig};
+///
+/// let state: Vec<char> = "FF+F".chars().collect();
+/// let segments = interpret(&state, &TurtleConfig::new(1.0, 90f64.to_radians()));
+/// let rect = bounds(&segments).unwrap();
+/// assert_eq!(2.0, rect.height());
+/// assert!((rect.min.0 + 1.0).abs() < 1e-9);
+///
+/// assert_eq!(None, bounds(&[]));
+/// ```
+pub fn bounds(segments: &[Segment]) -> Option<Rect> {
+    let mut points = segments.iter().flat_map(|s| iter::once(s.start).chain(iter::once(s.end)));
+    let first = points.next()?;
+    let mut rect = Rect {
+        min: first,
+        max: first,
+    };
+    for (x, y) in points {
+        rect.min = (rect.min.0.min(x), rect.min.1.min(y));
+        rect.max = (rect.max.0.max(x), rect.max.1.max(y));
+    }
+    Some(rect)
+}
+
+/// How far a pen travels to draw some segments, as measured by
+/// `path_lengths`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathLengths {
+    /// the total length of the segments, drawn with the pen down
+    pub drawn: f64,
+    /// the total length of the moves between segments with the pen up
+    pub travel: f64,
+    /// the number of times the pen is lifted to move between segments
+    pub lifts: usize,
+}
+
+/// Measure the distance a pen plotter covers drawing segments in order,
+/// for estimating how long a plot will take.
+///
+/// Whenever a segment does not start where the one before it ended, the
+/// pen is lifted and moved straight across, whether the turtle got there
+/// by moving without drawing or by returning from a branch.  These moves
+/// make up the travel, which starts from the start of the first segment.
+///
+/// The interpreters only emit the segments they draw, with no pen-up
+/// segments flagged as moves, so the travel is worked out from the gaps
+/// between segments rather than from the turtle's own moves.  Several moves
+/// in a row without drawing count as one straight move from the end of one
+/// segment to the start of the next, which is the path a plotter takes
+/// anyway, and moves after the last segment are not counted.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret, path_lengths, TurtleConfig};
+///
+/// // after the branch, the pen goes from its tip to the end of the move
+/// let state: Vec<char> = "F[+F]fF".chars().collect();
+/// let segments = interpret(&state, &TurtleConfig::new(1.0, 90f64.to_radians()));
+/// let lengths = path_lengths(&segments);
+/// assert!((lengths.drawn - 3.0).abs() < 1e-9);
+/// assert!((lengths.travel - 2f64.sqrt()).abs() < 1e-9);
+/// assert_eq!(1, lengths.lifts);
+/// ```
+pub fn path_lengths(segments: &[Segment]) -> PathLengths {
+    let mut lengths = PathLengths::default();
+    let mut pen = segments.first().map(|s| s.start);
+    for s in segments.iter() {
+        if let Some(at) = pen {
+            if at != s.start {
+                lengths.travel += distance(at, s.start);
+                lengths.lifts += 1;
+            }
+        }
+        lengths.drawn += distance(s.start, s.end);
+        pen = Some(s.end);
+    }
+    lengths
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Scale and translate segments so that they fit inside a canvas of
+/// `width` by `height`, with `margin` left clear on every side.
+///
+/// The canvas spans from the origin to `(width, height)` in the same
+/// coordinates as the segments, so y still points up.  The drawing is scaled
+/// by the same factor along both axes to keep its aspect ratio, as large as
+/// fits, and centered along the axis with room to spare.  A drawing with no
+/// extent, such as a single point, is centered without scaling.  Pen widths
+/// are left as they are.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{bounds, fit_to, interpret, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F+F".chars().collect();
+/// let segments = interpret(&state, &TurtleConfig::new(3.0, 90f64.to_radians()));
+/// let fitted = fit_to(&segments, 200.0, 100.0, 10.0);
+///
+/// let rect = bounds(&fitted).unwrap();
+/// assert!((rect.height() - 80.0).abs() < 1e-9);
+/// assert!((rect.min.0 - 60.0).abs() < 1e-9);
+/// assert!(fit_to(&[], 200.0, 100.0, 10.0).is_empty());
+/// ```
+pub fn fit_to(segments: &[Segment], width: f64, height: f64, margin: f64) -> Vec<Segment> {
+    let rect = match bounds(segments) {
+        Some(rect) => rect,
+        None => return Vec::new(),
+    };
+    let available = ((width - 2.0 * margin).max(0.0), (height - 2.0 * margin).max(0.0));
+    let scale = match (rect.width() > 0.0, rect.height() > 0.0) {
+        (true, true) => (available.0 / rect.width()).min(available.1 / rect.height()),
+        (true, false) => available.0 / rect.width(),
+        (false, true) => available.1 / rect.height(),
+        (false, false) => 1.0,
+    };
+    let from = rect.center();
+    let to = (width / 2.0, height / 2.0);
+    let place = |(x, y): (f64, f64)| ((x - from.0) * scale + to.0, (y - from.1) * scale + to.1);
+    segments.iter()
+        .map(|s| Segment {
+            start: place(s.start),
+            end: place(s.end),
+            ..*s
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: (f64, f64), end: (f64, f64)) -> Segment {
+        Segment { start, end, width: 2.0, color: 1 }
+    }
+
+    #[test]
+    fn test_fit_wide_drawing() {
+        let segments = vec![segment((-2.0, 1.0), (2.0, 1.0)), segment((2.0, 1.0), (2.0, 2.0))];
+        let fitted = fit_to(&segments, 100.0, 100.0, 10.0);
+        assert_eq!(Some(Rect { min: (10.0, 40.0), max: (90.0, 60.0) }), bounds(&fitted));
+        assert_eq!((2.0, 1), (fitted[0].width, fitted[0].color));
+    }
+
+    #[test]
+    fn test_fit_degenerate() {
+        let fitted = fit_to(&[segment((3.0, 3.0), (3.0, 3.0))], 10.0, 20.0, 1.0);
+        assert_eq!((5.0, 10.0), fitted[0].start);
+
+        let fitted = fit_to(&[segment((0.0, 0.0), (0.0, 4.0))], 10.0, 20.0, 2.0);
+        assert_eq!(Some(Rect { min: (5.0, 2.0), max: (5.0, 18.0) }), bounds(&fitted));
+    }
+
+    #[test]
+    fn test_path_lengths() {
+        assert_eq!(PathLengths::default(), path_lengths(&[]));
+        let segments = vec![
+            segment((1.0, 1.0), (4.0, 5.0)),
+            segment((4.0, 5.0), (4.0, 6.0)),
+            segment((0.0, 6.0), (0.0, 7.0)),
+            segment((0.0, 7.0), (0.0, 7.0)),
+            segment((3.0, 3.0), (0.0, 7.0)),
+        ];
+        let expected = PathLengths { drawn: 12.0, travel: 9.0, lifts: 2 };
+        assert_eq!(expected, path_lengths(&segments));
+    }
+}
@@ -0,0 +1,1165 @@
+//! Turtle graphics interpretation of L-system states.
+//!
+//! The turtle starts at the origin heading up the y axis, unless the
+//! `TurtleConfig` places it elsewhere, and reads the state one symbol at a
+//! time:
+//!
+//! | Symbol     | Action                                    |
+//! |------------|-------------------------------------------|
+//! | `F`, `G`   | move forward one step, drawing a line     |
+//! | `f`        | move forward one step without drawing     |
+//! | `+`        | turn left (counterclockwise) by the angle |
+//! | `-`        | turn right (clockwise) by the angle       |
+//! | `\|`       | turn around                               |
+//! | `[`        | push the turtle's state onto the stack    |
+//! | `]`        | pop the turtle's state from the stack     |
+//! | `` ` ``    | widen the pen by the width step           |
+//! | `'`        | narrow the pen by the width step          |
+//! | `;`        | move on to the next color index           |
+//! | `,`        | move back to the previous color index     |
+//!
+//! The pen's width and color index are stamped on every segment drawn, and
+//! are saved and restored by the brackets along with position and heading.
+//! Any other symbol is ignored.  A `]` with nothing on the stack is ignored
+//! too; use `try_interpret`, or `check_brackets` beforehand, to treat
+//! unbalanced brackets as an error instead.
+//!
+//! # Accuracy
+//!
+//! In the plane, the turtle counts how many times it has turned by the angle
+//! rather than adding the angle to its heading over and over, so its heading
+//! is never more than one rounding away from exact.  When the angle divides
+//! a full circle, as the 60 degrees of a Koch curve or the 90 degrees of a
+//! square do, the count wraps around and the turtle always uses one of the
+//! same few headings, so lines in the same direction are drawn with exactly
+//! the same heading and closed figures come back to their start up to the
+//! rounding of the positions added along the way.  In three dimensions the
+//! orientation frame is rotated and re-orthonormalized at each turn, so it
+//! drifts by a tiny amount per turn.
+//!
+//! In three dimensions, `interpret_3d` tracks a full orientation frame of
+//! heading, left and up vectors, and additionally understands:
+//!
+//! | Symbol     | Action                                    |
+//! |------------|-------------------------------------------|
+//! | `&`        | pitch down by the angle                   |
+//! | `^`        | pitch up by the angle                     |
+//! | `\`        | roll left by the angle                    |
+//! | `/`        | roll right by the angle                   |
+//!
+//! Here `+` and `-` yaw about the up vector, and the other commands work as
+//! they do in the plane.
+//!
+//! States of `Parametric` atoms are drawn by `interpret_parametric`, which
+//! reads the size of each step or turn from the atom's first parameter.
+//!
+//! Each symbol's meaning is a `TurtleAction`.  To use a different
+//! convention, start from `default_actions()`, change the table, and pass it
+//! to `interpret_with` or `interpret_3d_with`.
+//!
+//! # Examples
+//!
+//! ```
+//! use lsystem::turtle::{interpret, TurtleConfig};
+//!
+//! let square: Vec<char> = "F+F+F+F".chars().collect();
+//! let segments = interpret(&square, &TurtleConfig::new(1.0, 90f64.to_radians()));
+//! assert_eq!(4, segments.len());
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt;
+use std::hash::Hash;
+
+use {LRules, LSystem, Parametric};
+
+mod bounds;
+mod obj;
+#[cfg(feature = "image")]
+mod raster;
+mod svg;
+
+pub use self::bounds::{bounds, fit_to, path_lengths, PathLengths, Rect};
+pub use self::obj::to_obj;
+#[cfg(feature = "image")]
+pub use self::raster::rasterize;
+pub use self::svg::{polylines_to_svg, to_svg, SvgOptions};
+
+/// Parameters controlling how a turtle interprets a state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TurtleConfig {
+    /// distance travelled by each forward move
+    pub step: f64,
+    /// angle turned by each `+` or `-`, in radians
+    pub angle: f64,
+    /// pen width the turtle starts with
+    pub width: f64,
+    /// amount each `` ` `` or `'` changes the pen width by
+    pub width_step: f64,
+    /// direction the turtle starts out in, in radians counterclockwise from
+    /// the x axis; up the y axis by default.  In three dimensions the turtle
+    /// is yawed to this heading within the xy plane.
+    pub initial_heading: f64,
+    /// point the turtle starts at, the origin by default.  In three
+    /// dimensions the turtle starts at this point in the xy plane.
+    pub initial_position: (f64, f64),
+}
+
+impl TurtleConfig {
+    /// Create a new configuration from a step length and a turn angle in
+    /// radians, with a pen of width 1 that changes by 0.1 at a time, and a
+    /// turtle that starts at the origin heading up the y axis.
+    pub fn new(step: f64, angle: f64) -> TurtleConfig {
+        TurtleConfig {
+            step,
+            angle,
+            width: 1.0,
+            width_step: 0.1,
+            initial_heading: FRAC_PI_2,
+            initial_position: (0.0, 0.0),
+        }
+    }
+}
+
+impl Default for TurtleConfig {
+    /// A unit step and a right-angle turn, with a pen of width 1 that changes
+    /// by 0.1 at a time, and a turtle that starts at the origin heading up the
+    /// y axis.
+    fn default() -> TurtleConfig {
+        TurtleConfig::new(1.0, FRAC_PI_2)
+    }
+}
+
+/// Something a turtle can do in response to a symbol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TurtleAction {
+    /// move forward one step, drawing a line
+    Forward,
+    /// move forward one step without drawing
+    Move,
+    /// turn left (counterclockwise) by the angle
+    TurnLeft,
+    /// turn right (clockwise) by the angle
+    TurnRight,
+    /// pitch down by the angle; only used in three dimensions
+    PitchDown,
+    /// pitch up by the angle; only used in three dimensions
+    PitchUp,
+    /// roll left by the angle; only used in three dimensions
+    RollLeft,
+    /// roll right by the angle; only used in three dimensions
+    RollRight,
+    /// turn to face the opposite direction
+    TurnAround,
+    /// push the turtle's state onto the stack
+    Push,
+    /// pop the turtle's state from the stack
+    Pop,
+    /// widen the pen by the width step
+    Widen,
+    /// narrow the pen by the width step
+    Narrow,
+    /// move on to the next color index
+    NextColor,
+    /// move back to the previous color index
+    PreviousColor,
+    /// do nothing
+    NoOp,
+}
+
+impl TurtleAction {
+    /// The action a symbol stands for under the usual convention described
+    /// in the module documentation, or `NoOp` if it has none.
+    pub fn from_char(c: char) -> TurtleAction {
+        match c {
+            'F' | 'G' => TurtleAction::Forward,
+            'f' => TurtleAction::Move,
+            '+' => TurtleAction::TurnLeft,
+            '-' => TurtleAction::TurnRight,
+            '&' => TurtleAction::PitchDown,
+            '^' => TurtleAction::PitchUp,
+            '\\' => TurtleAction::RollLeft,
+            '/' => TurtleAction::RollRight,
+            '|' => TurtleAction::TurnAround,
+            '[' => TurtleAction::Push,
+            ']' => TurtleAction::Pop,
+            '`' => TurtleAction::Widen,
+            '\'' => TurtleAction::Narrow,
+            ';' => TurtleAction::NextColor,
+            ',' => TurtleAction::PreviousColor,
+            _ => TurtleAction::NoOp,
+        }
+    }
+}
+
+/// The usual symbol to action mapping described in the module
+/// documentation, as a table that can be changed and passed to
+/// `interpret_with`.
+pub fn default_actions() -> HashMap<char, TurtleAction> {
+    "FGf+-&^\\/|[]`';,".chars().map(|c| (c, TurtleAction::from_char(c))).collect()
+}
+
+/// A straight line drawn by the turtle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    /// width of the pen that drew the line
+    pub width: f64,
+    /// index of the pen color that drew the line
+    pub color: usize,
+}
+
+/// A connected run of lines drawn by the turtle without lifting or changing
+/// its pen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    /// the points visited, in order; there are always at least two
+    pub points: Vec<(f64, f64)>,
+    /// width of the pen that drew the lines
+    pub width: f64,
+    /// index of the pen color that drew the lines
+    pub color: usize,
+}
+
+/// The position, heading and pen of a turtle in the plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Turtle2D {
+    pub position: (f64, f64),
+    /// direction of travel, in radians counterclockwise from the x axis
+    pub heading: f64,
+    /// width of the pen
+    pub width: f64,
+    /// index of the pen color
+    pub color: usize,
+}
+
+impl Turtle2D {
+    /// Create a turtle at the origin, heading up the y axis, with a pen of
+    /// width 1 and color index 0.
+    pub fn new() -> Turtle2D {
+        Turtle2D {
+            position: (0.0, 0.0),
+            heading: FRAC_PI_2,
+            width: 1.0,
+            color: 0,
+        }
+    }
+
+    /// Move forward by `distance`, returning the segment travelled.
+    pub fn forward(&mut self, distance: f64) -> Segment {
+        let start = self.position;
+        let end = (start.0 + distance * self.heading.cos(),
+                   start.1 + distance * self.heading.sin());
+        self.position = end;
+        Segment {
+            start,
+            end,
+            width: self.width,
+            color: self.color,
+        }
+    }
+
+    /// Turn counterclockwise by `angle` radians.  Negative angles turn
+    /// clockwise.
+    ///
+    /// This simply adds to the heading, so many small turns can drift; the
+    /// interpreters count turns by the angle instead.
+    pub fn turn(&mut self, angle: f64) {
+        self.heading += angle;
+    }
+}
+
+impl Default for Turtle2D {
+    fn default() -> Turtle2D {
+        Turtle2D::new()
+    }
+}
+
+/// An atom that stands for a turtle command, which lets states over any
+/// alphabet be drawn.
+///
+/// `char` follows the usual convention described in the module
+/// documentation.  Atoms of a type from another crate, such as integers,
+/// cannot implement this trait outside this crate; draw them with
+/// `interpret_with` and a table instead, or wrap them in a type of your own.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret, AsTurtleCommand, TurtleAction, TurtleConfig};
+///
+/// enum Cell {
+///     Stem,
+///     Bud,
+///     Branch(bool),
+/// }
+///
+/// impl AsTurtleCommand for Cell {
+///     fn command(&self) -> TurtleAction {
+///         match *self {
+///             Cell::Stem => TurtleAction::Forward,
+///             Cell::Bud => TurtleAction::NoOp,
+///             Cell::Branch(true) => TurtleAction::Push,
+///             Cell::Branch(false) => TurtleAction::Pop,
+///         }
+///     }
+/// }
+///
+/// let state = vec![Cell::Stem, Cell::Branch(true), Cell::Stem, Cell::Bud,
+///                  Cell::Branch(false), Cell::Stem];
+/// assert_eq!(3, interpret(&state, &TurtleConfig::default()).len());
+/// ```
+pub trait AsTurtleCommand {
+    /// The action the turtle takes on reading this atom.
+    fn command(&self) -> TurtleAction;
+}
+
+impl AsTurtleCommand for char {
+    fn command(&self) -> TurtleAction {
+        TurtleAction::from_char(*self)
+    }
+}
+
+impl AsTurtleCommand for TurtleAction {
+    fn command(&self) -> TurtleAction {
+        *self
+    }
+}
+
+/// Interpret a state as turtle commands, returning the line segments drawn.
+pub fn interpret<T>(state: &[T], config: &TurtleConfig) -> Vec<Segment> where T: AsTurtleCommand {
+    draw_2d(state.iter().map(|c| (c.command(), None)), config)
+}
+
+/// Render every generation of an L-system as a frame of line segments, for
+/// animating its growth.
+///
+/// The system is reset to its axiom and run for up to `generations`
+/// generations, and each state is interpreted with `config`.  The first
+/// frame is the axiom, so there are `generations + 1` frames unless the
+/// system terminates early, in which case there is one frame per generation
+/// it reached.  Afterwards the system is restored to the generation it was
+/// at, along with any generation `next_partial` or `next_budgeted` was part
+/// way through, so the frames can be rendered at any point.
+///
+/// ```
+/// use lsystem::char_system;
+/// use lsystem::turtle::{render_frames, TurtleConfig};
+///
+/// let mut system = char_system("F", &[('F', "F+F")]);
+/// system.next();
+///
+/// let frames = render_frames(&mut system, 3, &TurtleConfig::default());
+/// let lengths: Vec<usize> = frames.iter().map(|f| f.len()).collect();
+/// assert_eq!(vec![1, 2, 4, 8], lengths);
+/// assert_eq!(1, system.generation());
+/// ```
+pub fn render_frames<T, P>(system: &mut LSystem<T, P>, generations: usize, config: &TurtleConfig) -> Vec<Vec<Segment>> where P: LRules<T>, T: Clone + AsTurtleCommand {
+    system.probe(|system, _| {
+        system.reset();
+        let mut frames = Vec::with_capacity(generations + 1);
+        frames.push(interpret(system.current_state(), config));
+        for _ in 0..generations {
+            match system.next_ref() {
+                Some(state) => frames.push(interpret(state, config)),
+                None => break,
+            }
+        }
+        frames
+    })
+}
+
+/// Interpret a state as turtle commands like `interpret`, looking up what
+/// each symbol does in `actions` instead of following the usual convention.
+/// Symbols missing from `actions` are ignored.
+///
+/// ```
+/// use lsystem::turtle::{default_actions, interpret_with, TurtleAction, TurtleConfig};
+///
+/// let mut actions = default_actions();
+/// actions.insert('X', TurtleAction::Forward);
+/// actions.insert('G', TurtleAction::Move);
+///
+/// let state: Vec<char> = "XGF".chars().collect();
+/// let segments = interpret_with(&state, &TurtleConfig::default(), &actions);
+/// assert_eq!(2, segments.len());
+/// assert_eq!(2.0, segments[1].start.1);
+/// ```
+pub fn interpret_with<T>(state: &[T], config: &TurtleConfig, actions: &HashMap<T, TurtleAction>) -> Vec<Segment> where T: Hash + Eq {
+    draw_2d(state.iter().map(|c| (actions.get(c).cloned().unwrap_or(TurtleAction::NoOp), None)), config)
+}
+
+/// Interpret a state of parametric atoms as turtle commands, taking the
+/// size of each action from the atom's first parameter.
+///
+/// The symbol of each atom picks the action as in `interpret`, and its first
+/// parameter, if it has one, replaces the configured amount: `F(l)` and
+/// `f(l)` move `l` forward, `+(a)` and `-(a)` turn by `a` radians, and
+/// `` `(w) `` and `'(w)` change the pen width by `w`.  An atom without
+/// parameters falls back to the configuration, and the other actions ignore
+/// parameters.  Turns by the configured angle keep the accuracy described in
+/// the module documentation; turns by a parameter are added to the heading.
+///
+/// ```
+/// use lsystem::turtle::{interpret_parametric, TurtleConfig};
+/// use lsystem::Parametric;
+///
+/// let state = vec![
+///     Parametric::new('F', vec![2.0]),
+///     Parametric::new('+', vec![90f64.to_radians()]),
+///     Parametric::new('F', vec![]),
+/// ];
+/// let segments = interpret_parametric(&state, &TurtleConfig::new(0.5, 0.0));
+/// assert_eq!(2.0, segments[0].end.1);
+/// assert!((segments[1].end.0 + 0.5).abs() < 1e-12);
+/// ```
+pub fn interpret_parametric<S>(state: &[Parametric<S>], config: &TurtleConfig) -> Vec<Segment> where S: AsTurtleCommand {
+    draw_2d(state.iter().map(|p| (p.symbol.command(), p.params.first().cloned())), config)
+}
+
+/// Draw in the plane, carrying out each action by the amount paired with it,
+/// or by the configured amount if it has none.
+fn draw_2d<I>(actions: I, config: &TurtleConfig) -> Vec<Segment> where I: Iterator<Item = (TurtleAction, Option<f64>)> {
+    let mut turtle = Turtle2D::new();
+    turtle.width = config.width;
+    turtle.position = config.initial_position;
+    turtle.heading = config.initial_heading;
+    let mut turns = Turns::new(config.angle, config.initial_heading);
+    let mut stack = Vec::new();
+    let mut segments = Vec::new();
+    for (action, amount) in actions {
+        match action {
+            TurtleAction::Forward => segments.push(turtle.forward(amount.unwrap_or(config.step))),
+            TurtleAction::Move => {
+                turtle.forward(amount.unwrap_or(config.step));
+            },
+            TurtleAction::TurnLeft => turtle.heading = match amount {
+                Some(angle) => turns.turn_by(angle),
+                None => turns.step(1),
+            },
+            TurtleAction::TurnRight => turtle.heading = match amount {
+                Some(angle) => turns.turn_by(-angle),
+                None => turns.step(-1),
+            },
+            TurtleAction::TurnAround => turtle.heading = turns.turn_around(),
+            TurtleAction::Widen => turtle.width += amount.unwrap_or(config.width_step),
+            TurtleAction::Narrow => turtle.width = (turtle.width - amount.unwrap_or(config.width_step)).max(0.0),
+            TurtleAction::NextColor => turtle.color += 1,
+            TurtleAction::PreviousColor => turtle.color = turtle.color.saturating_sub(1),
+            TurtleAction::Push => stack.push((turtle, turns)),
+            TurtleAction::Pop => {
+                if let Some((t, n)) = stack.pop() {
+                    turtle = t;
+                    turns = n;
+                }
+            },
+            TurtleAction::PitchDown | TurtleAction::PitchUp |
+            TurtleAction::RollLeft | TurtleAction::RollRight |
+            TurtleAction::NoOp => {},
+        }
+    }
+    segments
+}
+
+/// The heading of a turtle in the plane, counted in whole turns by the
+/// configured angle and whole half turns, so that it is worked out afresh
+/// after every turn instead of accumulating rounding error.
+#[derive(Clone, Copy)]
+struct Turns {
+    /// the heading before any turns
+    initial: f64,
+    angle: f64,
+    /// the number of turns by `angle` in a full circle, if it is a whole
+    /// number
+    period: Option<i64>,
+    steps: i64,
+    half_turns: i64,
+    /// the sum of the turns by other angles, which are not counted exactly
+    offset: f64,
+}
+
+impl Turns {
+    fn new(angle: f64, initial: f64) -> Turns {
+        let n = (2.0 * PI / angle).round();
+        let period = if n >= 1.0 && n < i64::MAX as f64 && (n * angle - 2.0 * PI).abs() < 1e-9 {
+            Some(n as i64)
+        } else {
+            None
+        };
+        Turns {
+            initial,
+            angle,
+            period,
+            steps: 0,
+            half_turns: 0,
+            offset: 0.0,
+        }
+    }
+
+    /// Turn by an angle other than the configured one, returning the new
+    /// heading.
+    fn turn_by(&mut self, angle: f64) -> f64 {
+        self.offset = (self.offset + angle) % (2.0 * PI);
+        self.heading()
+    }
+
+    /// Turn by `steps` times the angle, returning the new heading.
+    fn step(&mut self, steps: i64) -> f64 {
+        self.steps += steps;
+        if let Some(n) = self.period {
+            self.steps = self.steps.rem_euclid(n);
+        }
+        self.heading()
+    }
+
+    /// Turn to face the opposite direction, returning the new heading.
+    fn turn_around(&mut self) -> f64 {
+        match self.period {
+            Some(n) if n % 2 == 0 => self.step(n / 2),
+            _ => {
+                self.half_turns = (self.half_turns + 1) % 2;
+                self.heading()
+            },
+        }
+    }
+
+    fn heading(&self) -> f64 {
+        self.initial + self.steps as f64 * self.angle + self.half_turns as f64 * PI + self.offset
+    }
+}
+
+/// Interpret a state as turtle commands like `interpret`, joining the lines
+/// drawn into polylines.
+///
+/// A polyline carries on through turns, and breaks wherever the next line
+/// does not start where the last one ended, as after a `]` or an `f`, or
+/// where the pen changes width or color.  Drawing each polyline as a single
+/// shape takes far less output than drawing every line separately.
+///
+/// ```
+/// use lsystem::turtle::{interpret_polylines, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F[-F-F]fF".chars().collect();
+/// let polylines = interpret_polylines(&state, &TurtleConfig::default());
+/// let sizes: Vec<usize> = polylines.iter().map(|p| p.points.len()).collect();
+/// assert_eq!(vec![6, 2], sizes);
+/// ```
+pub fn interpret_polylines<T>(state: &[T], config: &TurtleConfig) -> Vec<Polyline> where T: AsTurtleCommand {
+    let mut polylines: Vec<Polyline> = Vec::new();
+    for s in interpret(state, config) {
+        if let Some(last) = polylines.last_mut() {
+            if last.points.last() == Some(&s.start) && last.width == s.width && last.color == s.color {
+                last.points.push(s.end);
+                continue;
+            }
+        }
+        polylines.push(Polyline {
+            points: vec![s.start, s.end],
+            width: s.width,
+            color: s.color,
+        });
+    }
+    polylines
+}
+
+/// Interpret a state as turtle commands like `interpret`, but fail if its
+/// brackets are unbalanced rather than ignoring them.
+///
+/// # Errors
+///
+/// Returns the `BracketError` from `check_brackets` if the state has a `]`
+/// with no matching `[`, or a `[` that is never closed.
+pub fn try_interpret<T>(state: &[T], config: &TurtleConfig) -> Result<Vec<Segment>, BracketError> where T: AsTurtleCommand {
+    check_brackets(state)?;
+    Ok(interpret(state, config))
+}
+
+/// A problem with the nesting of brackets in a state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BracketError {
+    /// the `]` at this index has no `[` to close
+    UnmatchedClose(usize),
+    /// this many `[` are never closed
+    Unclosed(usize),
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BracketError::UnmatchedClose(index) =>
+                write!(f, "unmatched `]` at index {}", index),
+            BracketError::Unclosed(count) =>
+                write!(f, "{} unclosed `[`", count),
+        }
+    }
+}
+
+impl Error for BracketError {}
+
+/// Check that every `[` in a state is closed by a later `]`, and every `]`
+/// closes an earlier `[`, so that a turtle never pops an empty stack.  For
+/// atoms other than `char`, the brackets are whichever atoms push and pop.
+///
+/// # Errors
+///
+/// Returns `BracketError::UnmatchedClose` with the index of the first `]`
+/// that has nothing to close, or otherwise `BracketError::Unclosed` with the
+/// number of `[` left open at the end.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{check_brackets, BracketError};
+///
+/// let state: Vec<char> = "F[+F[-F]]F".chars().collect();
+/// assert_eq!(Ok(()), check_brackets(&state));
+///
+/// let state: Vec<char> = "F[+F]]F".chars().collect();
+/// assert_eq!(Err(BracketError::UnmatchedClose(5)), check_brackets(&state));
+///
+/// let state: Vec<char> = "F[+F[-F".chars().collect();
+/// assert_eq!(Err(BracketError::Unclosed(2)), check_brackets(&state));
+/// ```
+pub fn check_brackets<T>(state: &[T]) -> Result<(), BracketError> where T: AsTurtleCommand {
+    let mut depth = 0;
+    for (i, atom) in state.iter().enumerate() {
+        match atom.command() {
+            TurtleAction::Push => depth += 1,
+            TurtleAction::Pop => {
+                if depth == 0 {
+                    return Err(BracketError::UnmatchedClose(i));
+                }
+                depth -= 1;
+            },
+            _ => {},
+        }
+    }
+    if depth == 0 {
+        Ok(())
+    } else {
+        Err(BracketError::Unclosed(depth))
+    }
+}
+
+/// A straight line drawn by a turtle in three dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment3D {
+    pub start: [f64; 3],
+    pub end: [f64; 3],
+    /// width of the pen that drew the line
+    pub width: f64,
+    /// index of the pen color that drew the line
+    pub color: usize,
+}
+
+/// The position, orientation and pen of a turtle in space.  The orientation is
+/// kept as an orthonormal frame of heading, left and up vectors, which is
+/// re-orthonormalized after every rotation so that long sequences of turns do
+/// not drift.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Turtle3D {
+    pub position: [f64; 3],
+    pub heading: [f64; 3],
+    pub left: [f64; 3],
+    pub up: [f64; 3],
+    /// width of the pen
+    pub width: f64,
+    /// index of the pen color
+    pub color: usize,
+}
+
+impl Turtle3D {
+    /// Create a turtle at the origin, heading up the y axis with its left
+    /// along the negative x axis, matching `Turtle2D` in the xy plane.
+    pub fn new() -> Turtle3D {
+        Turtle3D {
+            position: [0.0, 0.0, 0.0],
+            heading: [0.0, 1.0, 0.0],
+            left: [-1.0, 0.0, 0.0],
+            up: [0.0, 0.0, 1.0],
+            width: 1.0,
+            color: 0,
+        }
+    }
+
+    /// Move forward by `distance`, returning the segment travelled.
+    pub fn forward(&mut self, distance: f64) -> Segment3D {
+        let start = self.position;
+        let end = add(start, scale(self.heading, distance));
+        self.position = end;
+        Segment3D {
+            start,
+            end,
+            width: self.width,
+            color: self.color,
+        }
+    }
+
+    /// Rotate about the up vector, turning left for positive angles.
+    pub fn yaw(&mut self, angle: f64) {
+        let (h, l) = rotate(self.heading, self.left, angle);
+        self.heading = h;
+        self.left = l;
+        self.orthonormalize();
+    }
+
+    /// Rotate about the left vector, pitching down for positive angles.
+    pub fn pitch(&mut self, angle: f64) {
+        let (h, u) = rotate(self.heading, self.up, -angle);
+        self.heading = h;
+        self.up = u;
+        self.orthonormalize();
+    }
+
+    /// Rotate about the heading vector, rolling left for positive angles.
+    pub fn roll(&mut self, angle: f64) {
+        let (u, l) = rotate(self.up, self.left, angle);
+        self.up = u;
+        self.left = l;
+        self.orthonormalize();
+    }
+
+    /// Turn around to face the opposite direction.
+    pub fn turn_around(&mut self) {
+        self.heading = scale(self.heading, -1.0);
+        self.left = scale(self.left, -1.0);
+    }
+
+    fn orthonormalize(&mut self) {
+        self.heading = normalize(self.heading);
+        let l = sub(self.left, scale(self.heading, dot(self.left, self.heading)));
+        self.left = normalize(l);
+        self.up = cross(self.heading, self.left);
+    }
+}
+
+impl Default for Turtle3D {
+    fn default() -> Turtle3D {
+        Turtle3D::new()
+    }
+}
+
+/// Interpret a state as three dimensional turtle commands, returning the
+/// line segments drawn.
+pub fn interpret_3d<T>(state: &[T], config: &TurtleConfig) -> Vec<Segment3D> where T: AsTurtleCommand {
+    draw_3d(state.iter().map(AsTurtleCommand::command), config)
+}
+
+/// Interpret a state as three dimensional turtle commands like
+/// `interpret_3d`, looking up what each symbol does in `actions` instead of
+/// following the usual convention.  Symbols missing from `actions` are
+/// ignored.
+pub fn interpret_3d_with<T>(state: &[T], config: &TurtleConfig, actions: &HashMap<T, TurtleAction>) -> Vec<Segment3D> where T: Hash + Eq {
+    draw_3d(state.iter().map(|c| actions.get(c).cloned().unwrap_or(TurtleAction::NoOp)), config)
+}
+
+fn draw_3d<I>(actions: I, config: &TurtleConfig) -> Vec<Segment3D> where I: Iterator<Item = TurtleAction> {
+    let mut turtle = Turtle3D::new();
+    turtle.width = config.width;
+    turtle.position = [config.initial_position.0, config.initial_position.1, 0.0];
+    if config.initial_heading != FRAC_PI_2 {
+        turtle.yaw(config.initial_heading - FRAC_PI_2);
+    }
+    let mut stack = Vec::new();
+    let mut segments = Vec::new();
+    for action in actions {
+        match action {
+            TurtleAction::Forward => segments.push(turtle.forward(config.step)),
+            TurtleAction::Move => {
+                turtle.forward(config.step);
+            },
+            TurtleAction::TurnLeft => turtle.yaw(config.angle),
+            TurtleAction::TurnRight => turtle.yaw(-config.angle),
+            TurtleAction::PitchDown => turtle.pitch(config.angle),
+            TurtleAction::PitchUp => turtle.pitch(-config.angle),
+            TurtleAction::RollLeft => turtle.roll(config.angle),
+            TurtleAction::RollRight => turtle.roll(-config.angle),
+            TurtleAction::TurnAround => turtle.turn_around(),
+            TurtleAction::Widen => turtle.width += config.width_step,
+            TurtleAction::Narrow => turtle.width = (turtle.width - config.width_step).max(0.0),
+            TurtleAction::NextColor => turtle.color += 1,
+            TurtleAction::PreviousColor => turtle.color = turtle.color.saturating_sub(1),
+            TurtleAction::Push => stack.push(turtle),
+            TurtleAction::Pop => {
+                if let Some(t) = stack.pop() {
+                    turtle = t;
+                }
+            },
+            TurtleAction::NoOp => {},
+        }
+    }
+    segments
+}
+
+/// Interpret a state as three dimensional turtle commands like
+/// `interpret_3d`, but fail if its brackets are unbalanced rather than
+/// ignoring them.
+///
+/// # Errors
+///
+/// Returns the `BracketError` from `check_brackets` if the state has a `]`
+/// with no matching `[`, or a `[` that is never closed.
+pub fn try_interpret_3d<T>(state: &[T], config: &TurtleConfig) -> Result<Vec<Segment3D>, BracketError> where T: AsTurtleCommand {
+    check_brackets(state)?;
+    Ok(interpret_3d(state, config))
+}
+
+/// Rotate the pair of orthogonal vectors `(a, b)` by `angle` within the
+/// plane they span, turning `a` towards `b`.
+fn rotate(a: [f64; 3], b: [f64; 3], angle: f64) -> ([f64; 3], [f64; 3]) {
+    let (sin, cos) = angle.sin_cos();
+    (add(scale(a, cos), scale(b, sin)), sub(scale(b, cos), scale(a, sin)))
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], k: f64) -> [f64; 3] {
+    [a[0] * k, a[1] * k, a[2] * k]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1],
+     a[2] * b[0] - a[0] * b[2],
+     a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    scale(a, 1.0 / dot(a, a).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {LSystem, MapRules, ParametricMapRules, Progress};
+
+    fn assert_near(expected: (f64, f64), actual: (f64, f64)) {
+        assert!((expected.0 - actual.0).abs() < 1e-9 && (expected.1 - actual.1).abs() < 1e-9,
+                "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn test_square() {
+        let state: Vec<char> = "F+F+F+F".chars().collect();
+        let segments = interpret(&state, &TurtleConfig::default());
+        assert_eq!(4, segments.len());
+        assert_near((0.0, 1.0), segments[0].end);
+        assert_near((-1.0, 1.0), segments[1].end);
+        assert_near((-1.0, 0.0), segments[2].end);
+        assert_near((0.0, 0.0), segments[3].end);
+    }
+
+    #[test]
+    fn test_move_and_ignore() {
+        let state: Vec<char> = "FfXG".chars().collect();
+        let segments = interpret(&state, &TurtleConfig::new(2.0, 1.0));
+        assert_eq!(2, segments.len());
+        assert_near((0.0, 4.0), segments[1].start);
+        assert_near((0.0, 6.0), segments[1].end);
+    }
+
+    #[test]
+    fn test_render_frames_terminates() {
+        let mut rules = MapRules::new();
+        rules.set_str('A', "F+F");
+        let mut system = LSystem::new(rules, vec!['A', 'F']);
+        let frames = render_frames(&mut system, 5, &TurtleConfig::default());
+        assert_eq!(2, frames.len());
+        assert_eq!(1, frames[0].len());
+        assert_eq!(3, frames[1].len());
+        assert_eq!(0, system.generation());
+        assert_eq!(&['A', 'F'], system.current_state());
+    }
+
+    #[test]
+    fn test_render_frames_keeps_progress() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "FF");
+        let mut system = LSystem::new(rules, vec!['F', 'F']);
+        assert_eq!(Progress::InProgress, system.next_budgeted(1));
+        render_frames(&mut system, 2, &TurtleConfig::default());
+        assert_eq!(Progress::Done(vec!['F'; 4]), system.next_budgeted(1));
+        assert_eq!(1, system.generation());
+    }
+
+    #[test]
+    fn test_brackets() {
+        let state: Vec<char> = "F[+F]F".chars().collect();
+        let segments = interpret(&state, &TurtleConfig::default());
+        assert_eq!(3, segments.len());
+        assert_near((-1.0, 1.0), segments[1].end);
+        assert_near((0.0, 1.0), segments[2].start);
+        assert_near((0.0, 2.0), segments[2].end);
+    }
+
+    #[test]
+    fn test_try_interpret() {
+        let state: Vec<char> = "F[+F]F".chars().collect();
+        assert_eq!(Ok(interpret(&state, &TurtleConfig::default())),
+                   try_interpret(&state, &TurtleConfig::default()));
+
+        let state: Vec<char> = "F]F[".chars().collect();
+        assert_eq!(2, interpret(&state, &TurtleConfig::default()).len());
+        assert_eq!(Err(BracketError::UnmatchedClose(1)),
+                   try_interpret(&state, &TurtleConfig::default()));
+        assert_eq!(Err(BracketError::UnmatchedClose(1)),
+                   try_interpret_3d(&state, &TurtleConfig::default()));
+        assert_eq!(Err(BracketError::Unclosed(1)), check_brackets(&state[2..]));
+    }
+
+    #[test]
+    fn test_pen_stack() {
+        let state: Vec<char> = "``F[';F]F'''''''''''''''F".chars().collect();
+        let segments = interpret(&state, &TurtleConfig::default());
+        let pens: Vec<(f64, usize)> = segments.iter()
+            .map(|s| ((s.width * 10.0).round() / 10.0, s.color))
+            .collect();
+        assert_eq!(vec![(1.2, 0), (1.1, 1), (1.2, 0), (0.0, 0)], pens);
+
+        let segments = interpret_3d(&state, &TurtleConfig::default());
+        assert_eq!(1, segments[1].color);
+        assert_eq!(0, segments[2].color);
+    }
+
+    #[test]
+    fn test_polylines() {
+        let state: Vec<char> = "F[+F]F';F".chars().collect();
+        let polylines = interpret_polylines(&state, &TurtleConfig::default());
+        assert_eq!(3, polylines.len());
+        assert_eq!(3, polylines[0].points.len());
+        assert_near((-1.0, 1.0), polylines[0].points[2]);
+        assert_eq!(2, polylines[1].points.len());
+        assert_near((0.0, 2.0), polylines[1].points[1]);
+        assert_eq!(1, polylines[2].color);
+
+        let segments = interpret(&state, &TurtleConfig::default());
+        let lines: usize = polylines.iter().map(|p| p.points.len() - 1).sum();
+        assert_eq!(segments.len(), lines);
+    }
+
+    #[test]
+    fn test_default_actions() {
+        let actions = default_actions();
+        let state: Vec<char> = "F[+F&F]|F-fX;`F".chars().collect();
+        assert_eq!(interpret(&state, &TurtleConfig::default()),
+                   interpret_with(&state, &TurtleConfig::default(), &actions));
+        assert_eq!(interpret_3d(&state, &TurtleConfig::default()),
+                   interpret_3d_with(&state, &TurtleConfig::default(), &actions));
+        assert_eq!(16, actions.len());
+    }
+
+    #[test]
+    fn test_remapped_actions() {
+        let mut actions = HashMap::new();
+        actions.insert('A', TurtleAction::Forward);
+        actions.insert('B', TurtleAction::TurnRight);
+        let state: Vec<char> = "ABAF+A".chars().collect();
+        let segments = interpret_with(&state, &TurtleConfig::default(), &actions);
+        assert_eq!(3, segments.len());
+        assert_near((1.0, 1.0), segments[1].end);
+        assert_near((2.0, 1.0), segments[2].end);
+    }
+
+    #[test]
+    fn test_koch_snowflake_closes() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F+F--F+F");
+        let mut system = LSystem::new(rules, "F--F--F".chars().collect());
+        let config = TurtleConfig::new(1.0, 60f64.to_radians());
+        let state = system.nth(4).unwrap();
+        let segments = interpret(&state, &config);
+        assert_eq!(3 * 4usize.pow(5), segments.len());
+        assert_near((0.0, 0.0), segments.last().unwrap().end);
+    }
+
+    #[test]
+    fn test_headings_repeat_exactly() {
+        let mut turns = Turns::new(60f64.to_radians(), FRAC_PI_2);
+        let start = turns.heading();
+        let mut headings = Vec::new();
+        for _ in 0..600 {
+            headings.push(turns.step(1));
+        }
+        assert_eq!(start.to_bits(), headings[599].to_bits());
+        assert_eq!(headings[0].to_bits(), headings[6].to_bits());
+        assert_eq!(headings[2].to_bits(), turns.turn_around().to_bits());
+        assert_eq!(headings[1].to_bits(), turns.step(-601).to_bits());
+
+        let mut turns = Turns::new(25f64.to_radians(), FRAC_PI_2);
+        assert_eq!(None, turns.period);
+        turns.step(1000);
+        assert!((turns.heading() - (FRAC_PI_2 + 1000.0 * 25f64.to_radians())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_turn_around() {
+        for &degrees in [60.0, 90.0, 25.0].iter() {
+            let state: Vec<char> = "F+|-F".chars().collect();
+            let segments = interpret(&state, &TurtleConfig::new(1.0, f64::to_radians(degrees)));
+            assert_near((0.0, 0.0), segments[1].end);
+        }
+    }
+
+    #[test]
+    fn test_integer_atoms() {
+        let mut actions = HashMap::new();
+        actions.insert(0u32, TurtleAction::Forward);
+        actions.insert(1, TurtleAction::TurnLeft);
+        actions.insert(2, TurtleAction::Push);
+        actions.insert(3, TurtleAction::Pop);
+        let state = vec![0, 2, 1, 0, 3, 0, 7];
+        let chars: Vec<char> = "F[+F]F".chars().collect();
+        assert_eq!(interpret(&chars, &TurtleConfig::default()),
+                   interpret_with(&state, &TurtleConfig::default(), &actions));
+
+        let commands: Vec<TurtleAction> = chars.iter().map(AsTurtleCommand::command).collect();
+        assert_eq!(interpret_3d(&chars, &TurtleConfig::default()),
+                   interpret_3d(&commands, &TurtleConfig::default()));
+        assert_eq!(Err(BracketError::UnmatchedClose(0)), check_brackets(&[TurtleAction::Pop]));
+    }
+
+    fn assert_near_3d(expected: [f64; 3], actual: [f64; 3]) {
+        for i in 0..3 {
+            assert!((expected[i] - actual[i]).abs() < 1e-9,
+                    "expected {:?}, got {:?}", expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_3d_closed_path() {
+        // a square in the xy plane, then one rising out of it
+        let state: Vec<char> = "F+F+F+F&F^F^F^F".chars().collect();
+        let segments = interpret_3d(&state, &TurtleConfig::default());
+        assert_eq!(8, segments.len());
+        assert_near_3d([0.0, 1.0, 0.0], segments[0].end);
+        assert_near_3d([-1.0, 1.0, 0.0], segments[1].end);
+        assert_near_3d([0.0, 0.0, 0.0], segments[3].end);
+        assert_near_3d([0.0, 0.0, -1.0], segments[4].end);
+        assert_near_3d([0.0, 0.0, 0.0], segments[7].end);
+    }
+
+    #[test]
+    fn test_3d_drift() {
+        // a full circle made of many small turns about each axis in turn
+        // should bring the turtle back to its starting orientation
+        let angle = 0.1f64.to_radians();
+        let mut turtle = Turtle3D::new();
+        for _ in 0..3600 {
+            turtle.yaw(angle);
+        }
+        for _ in 0..3600 {
+            turtle.pitch(angle);
+        }
+        for _ in 0..3600 {
+            turtle.roll(angle);
+        }
+        let start = Turtle3D::new();
+        assert_near_3d(start.heading, turtle.heading);
+        assert_near_3d(start.left, turtle.left);
+        assert_near_3d(start.up, turtle.up);
+    }
+
+    #[test]
+    fn test_3d_closed_polygon() {
+        // a 360-gon tilted out of the xy plane returns to the origin
+        let mut state: Vec<char> = "/&".chars().collect();
+        for _ in 0..360 {
+            state.extend("F+".chars());
+        }
+        let segments = interpret_3d(&state, &TurtleConfig::new(1.0, 1f64.to_radians()));
+        assert_eq!(360, segments.len());
+        assert!(segments[0].end[2].abs() > 1e-3);
+        assert_near_3d([0.0, 0.0, 0.0], segments[359].end);
+    }
+
+    #[test]
+    fn test_3d_brackets() {
+        let state: Vec<char> = "F[&F/F]F".chars().collect();
+        let segments = interpret_3d(&state, &TurtleConfig::default());
+        assert_eq!(4, segments.len());
+        assert_near_3d([0.0, 1.0, 0.0], segments[3].start);
+        assert_near_3d([0.0, 2.0, 0.0], segments[3].end);
+    }
+
+    #[test]
+    fn test_3d_turn_around() {
+        let state: Vec<char> = "F|F".chars().collect();
+        let segments = interpret_3d(&state, &TurtleConfig::default());
+        assert_near_3d([0.0, 0.0, 0.0], segments[1].end);
+    }
+
+    #[test]
+    fn test_generations() {
+        let mut rules = MapRules::new();
+        rules.set_str('F', "F[+F]F[-F]F");
+        let mut system = LSystem::new(rules, vec!['F']);
+        let config = TurtleConfig::new(1.0, 25f64.to_radians());
+        let mut expected = 1;
+        for _ in 0..4 {
+            expected *= 5;
+            let state = system.next().unwrap();
+            assert_eq!(expected, interpret(&state, &config).len());
+        }
+    }
+
+    #[test]
+    fn test_initial_placement() {
+        let state: Vec<char> = "F[+F]fF-F".chars().collect();
+        let config = TurtleConfig::new(1.0, 30f64.to_radians());
+        let placed = TurtleConfig {
+            initial_heading: FRAC_PI_2 + 1.0,
+            initial_position: (5.0, -2.0),
+            ..config
+        };
+        let rotate = |(x, y): (f64, f64)| (x * 1f64.cos() - y * 1f64.sin() + 5.0, x * 1f64.sin() + y * 1f64.cos() - 2.0);
+        let plain = interpret(&state, &config);
+        let moved = interpret(&state, &placed);
+        assert_eq!(plain.len(), moved.len());
+        for (p, m) in plain.iter().zip(moved.iter()) {
+            assert_near(rotate(p.start), m.start);
+            assert_near(rotate(p.end), m.end);
+        }
+
+        let moved_3d = interpret_3d(&state, &placed);
+        for (m, m3) in moved.iter().zip(moved_3d.iter()) {
+            assert_near(m.end, (m3.end[0], m3.end[1]));
+            assert_eq!(0.0, m3.end[2]);
+        }
+    }
+
+    #[test]
+    fn test_parametric_tapering_stem() {
+        // A(l) -> F(l) '(0.25) A(l/2)
+        let mut rules = ParametricMapRules::new();
+        rules.set('A', |p: &[f64]| Some(vec![
+            Parametric::new('F', vec![p[0]]),
+            Parametric::new('\'', vec![0.25]),
+            Parametric::new('A', vec![p[0] / 2.0]),
+        ]));
+        let mut system = LSystem::new(rules, vec![Parametric::new('A', vec![1.0])]);
+        let mut state = system.nth(2).unwrap();
+        // a bare turn and step use the configured angle and step
+        state.push(Parametric::new('+', vec![]));
+        state.push(Parametric::new('F', vec![]));
+
+        let segments = interpret_parametric(&state, &TurtleConfig::new(0.1, 90f64.to_radians()));
+        let widths: Vec<f64> = segments.iter().map(|s| s.width).collect();
+        assert_eq!(vec![1.0, 0.75, 0.5, 0.25], widths);
+        assert_near((0.0, 1.0), segments[0].end);
+        assert_near((0.0, 1.5), segments[1].end);
+        assert_near((0.0, 1.75), segments[2].end);
+        assert_near((-0.1, 1.75), segments[3].end);
+    }
+}
@@ -0,0 +1,134 @@
+//! Wavefront OBJ export of three dimensional turtle output.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::Segment3D;
+
+/// How close two points must be, along every axis, to be written as the same
+/// vertex.
+const EPSILON: f64 = 1e-9;
+
+/// Render three dimensional segments as a Wavefront OBJ file, which 3D tools
+/// such as Blender can import.
+///
+/// Every distinct end point becomes one `v` vertex, and every segment an `l`
+/// line between two of them.  Points within a billionth of a unit of each
+/// other along every axis are merged into the vertex seen first, so branches
+/// that meet up after a different path of turns still share a vertex
+/// despite rounding.  A segment whose ends merge into one vertex is left
+/// out.  Pen width and color are not written, as OBJ lines have neither.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret_3d, to_obj, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F+F".chars().collect();
+/// let obj = to_obj(&interpret_3d(&state, &TurtleConfig::default()));
+/// assert_eq!(4, obj.lines().filter(|l| l.starts_with("v ")).count());
+/// assert!(obj.ends_with("l 4 1\n"));
+/// ```
+pub fn to_obj(segments: &[Segment3D]) -> String {
+    let mut vertices = Vertices::default();
+    let lines: Vec<(usize, usize)> = segments.iter()
+        .map(|s| (vertices.index(s.start), vertices.index(s.end)))
+        .filter(|&(a, b)| a != b)
+        .collect();
+
+    let mut out = String::new();
+    // writing to a String cannot fail
+    for p in vertices.points.iter() {
+        let _ = writeln!(out, "v {} {} {}", p[0], p[1], p[2]);
+    }
+    for &(a, b) in lines.iter() {
+        let _ = writeln!(out, "l {} {}", a, b);
+    }
+    out
+}
+
+/// The distinct points written so far, found by the cell of a grid of
+/// `EPSILON` spacing they fall in.
+#[derive(Default)]
+struct Vertices {
+    points: Vec<[f64; 3]>,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl Vertices {
+    /// The 1-based OBJ index of the vertex for a point, adding one if no
+    /// vertex is near enough.
+    fn index(&mut self, p: [f64; 3]) -> usize {
+        let cell = [cell_of(p[0]), cell_of(p[1]), cell_of(p[2])];
+        // a near point can fall just across a cell boundary, so look in the
+        // neighboring cells too
+        for dx in -1..2 {
+            for dy in -1..2 {
+                for dz in -1..2 {
+                    let neighbor = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    for &i in self.cells.get(&neighbor).into_iter().flatten() {
+                        let q = self.points[i];
+                        if (0..3).all(|k| (p[k] - q[k]).abs() <= EPSILON) {
+                            return i + 1;
+                        }
+                    }
+                }
+            }
+        }
+        self.points.push(p);
+        self.cells.entry(cell).or_default().push(self.points.len() - 1);
+        self.points.len()
+    }
+}
+
+fn cell_of(x: f64) -> i64 {
+    (x / EPSILON).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turtle::{interpret_3d, TurtleConfig};
+
+    fn segment(start: [f64; 3], end: [f64; 3]) -> Segment3D {
+        Segment3D {
+            start,
+            end,
+            width: 1.0,
+            color: 0,
+        }
+    }
+
+    #[test]
+    fn test_near_points_merge() {
+        // the triangle closes a little off its start, the fourth line is too
+        // short to keep, and the fifth starts in the next grid cell over from
+        // where the fourth began
+        let segments = [
+            segment([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            segment([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            segment([0.0, 1.0, 0.0], [1e-12, -1e-12, 0.0]),
+            segment([0.0, 0.0, 2.0], [0.0, 0.0, 2.0 + 0.49e-9]),
+            segment([0.0, 0.0, 2.0 + 0.51e-9], [0.0, 0.0, 3.0]),
+        ];
+        let obj = to_obj(&segments);
+        let vertices: Vec<&str> = obj.lines().filter(|l| l.starts_with("v ")).collect();
+        let lines: Vec<&str> = obj.lines().filter(|l| l.starts_with("l ")).collect();
+        assert_eq!(vec!["v 0 0 0", "v 1 0 0", "v 0 1 0", "v 0 0 2", "v 0 0 3"], vertices);
+        assert_eq!(vec!["l 1 2", "l 2 3", "l 3 1", "l 4 5"], lines);
+    }
+
+    #[test]
+    fn test_turtle_branches() {
+        let state: Vec<char> = "F[+F][-F]&F".chars().collect();
+        let obj = to_obj(&interpret_3d(&state, &TurtleConfig::default()));
+        assert_eq!(5, obj.lines().filter(|l| l.starts_with("v ")).count());
+        assert!(obj.contains("l 1 2\nl 2 3\nl 2 4\nl 2 5\n"));
+    }
+
+    #[test]
+    fn test_degenerate_segments() {
+        assert_eq!("v 1 0 0\n", to_obj(&[segment([1.0, 0.0, 0.0], [1.0, 1e-12, 0.0])]));
+        assert_eq!("", to_obj(&[]));
+    }
+}
@@ -0,0 +1,99 @@
+//! Rasterizing turtle output to an image.
+
+use image::{GrayImage, Luma};
+
+use super::{fit_to, Segment};
+
+/// Draw segments as black, one pixel wide lines on a white image of `width`
+/// by `height` pixels, which can then be saved as a PNG with `save`.
+///
+/// The drawing is scaled to fill the image, keeping its aspect ratio, as
+/// `fit_to` does, and flipped vertically to suit images, whose y axis points
+/// down.  Pen widths and colors are not drawn.  An empty list of segments,
+/// or an image with no pixels, gives a blank image.
+///
+/// This requires the `image` feature.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret, rasterize, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F+F".chars().collect();
+/// let segments = interpret(&state, &TurtleConfig::default());
+/// let image = rasterize(&segments, 8, 8);
+///
+/// // the square's outline touches every edge of the image
+/// assert_eq!(0, image.get_pixel(0, 3)[0]);
+/// assert_eq!(0, image.get_pixel(7, 7)[0]);
+/// assert_eq!(255, image.get_pixel(3, 3)[0]);
+/// ```
+pub fn rasterize(segments: &[Segment], width: u32, height: u32) -> GrayImage {
+    let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+    if width == 0 || height == 0 {
+        return image;
+    }
+    // pixel centers sit on whole numbers, so the drawing spans from the
+    // center of the first pixel to the center of the last
+    let fitted = fit_to(segments, f64::from(width - 1), f64::from(height - 1), 0.0);
+    let flip = |(x, y): (f64, f64)| (x.round() as i64, i64::from(height - 1) - y.round() as i64);
+    for s in fitted.iter() {
+        draw_line(&mut image, flip(s.start), flip(s.end));
+    }
+    image
+}
+
+/// Draw a line between two pixels with Bresenham's algorithm, skipping any
+/// pixels outside the image.
+fn draw_line(image: &mut GrayImage, start: (i64, i64), end: (i64, i64)) {
+    let (mut x, mut y) = start;
+    let dx = (end.0 - x).abs();
+    let dy = -(end.1 - y).abs();
+    let sx = if x < end.0 { 1 } else { -1 };
+    let sy = if y < end.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        if x >= 0 && y >= 0 && x < i64::from(image.width()) && y < i64::from(image.height()) {
+            image.put_pixel(x as u32, y as u32, Luma([0]));
+        }
+        if (x, y) == end {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: (f64, f64), end: (f64, f64)) -> Segment {
+        Segment { start, end, width: 1.0, color: 0 }
+    }
+
+    fn black(image: &GrayImage) -> Vec<(u32, u32)> {
+        image.enumerate_pixels().filter(|p| p.2[0] == 0).map(|p| (p.0, p.1)).collect()
+    }
+
+    #[test]
+    fn test_diagonal_is_flipped() {
+        // y points up in turtle coordinates, so a rising line runs from the
+        // bottom left pixel to the top right
+        let image = rasterize(&[segment((0.0, 0.0), (2.0, 2.0))], 3, 3);
+        assert_eq!(vec![(2, 0), (1, 1), (0, 2)], black(&image));
+    }
+
+    #[test]
+    fn test_blank() {
+        assert!(black(&rasterize(&[], 4, 4)).is_empty());
+        assert_eq!((0, 5), rasterize(&[segment((0.0, 0.0), (1.0, 0.0))], 0, 5).dimensions());
+    }
+}
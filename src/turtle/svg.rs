@@ -0,0 +1,249 @@
+//! SVG export of turtle output.
+
+use std::fmt::Write;
+use std::iter;
+
+use super::{Polyline, Segment};
+
+/// Styling options for `to_svg`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    /// space left around the drawing, in drawing units
+    pub margin: f64,
+    pub stroke_width: f64,
+    /// any SVG color, e.g. `"black"` or `"#2a5d1f"`
+    pub stroke: String,
+    /// fill color for the whole canvas, or `None` for a transparent one
+    pub background: Option<String>,
+    /// colors for the turtle's color indices; segments whose index is past
+    /// the end use `stroke`
+    pub palette: Vec<String>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions {
+            margin: 1.0,
+            stroke_width: 0.1,
+            stroke: "black".to_string(),
+            background: None,
+            palette: Vec::new(),
+        }
+    }
+}
+
+/// Render segments as a standalone SVG document.
+///
+/// The viewBox tightly bounds the segments plus the margin.  Turtle
+/// coordinates have y pointing up, so the drawing is flipped vertically to
+/// suit SVG, whose y axis points down.  An empty list of segments produces an
+/// empty drawing of just the margin.
+///
+/// Each segment's pen width scales `stroke_width`, and its color index picks
+/// a color from `palette`.  Lines drawn with the default pen of width 1 and
+/// a color outside the palette carry no styling of their own.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret, to_svg, SvgOptions, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F+F".chars().collect();
+/// let segments = interpret(&state, &TurtleConfig::default());
+/// let svg = to_svg(&segments, &SvgOptions::default());
+/// assert!(svg.contains("viewBox=\"-2 -2 3 3\""));
+/// ```
+pub fn to_svg(segments: &[Segment], opts: &SvgOptions) -> String {
+    let points = segments.iter().flat_map(|s| iter::once(s.start).chain(iter::once(s.end)));
+    let mut out = header(points, opts);
+    for s in segments.iter() {
+        let _ = write!(out, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                       s.start.0, flip(s.start.1), s.end.0, flip(s.end.1));
+        pen(&mut out, s.width, s.color, opts);
+    }
+    footer(&mut out);
+    out
+}
+
+/// Render polylines as a standalone SVG document, with one `<polyline>`
+/// element each.  The layout and styling are the same as for `to_svg`.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::turtle::{interpret_polylines, polylines_to_svg, SvgOptions, TurtleConfig};
+///
+/// let state: Vec<char> = "F+F+F+F".chars().collect();
+/// let polylines = interpret_polylines(&state, &TurtleConfig::default());
+/// let svg = polylines_to_svg(&polylines, &SvgOptions::default());
+/// assert_eq!(1, svg.matches("<polyline").count());
+/// ```
+pub fn polylines_to_svg(polylines: &[Polyline], opts: &SvgOptions) -> String {
+    let points = polylines.iter().flat_map(|p| p.points.iter().cloned());
+    let mut out = header(points, opts);
+    for p in polylines.iter() {
+        let _ = write!(out, "<polyline points=\"");
+        for (i, &(x, y)) in p.points.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let _ = write!(out, "{},{}", x, flip(y));
+        }
+        out.push('"');
+        pen(&mut out, p.width, p.color, opts);
+    }
+    footer(&mut out);
+    out
+}
+
+/// Start an SVG document whose viewBox bounds `points` plus the margin, up
+/// to and including the opening tag of the group that holds the drawing.
+fn header<I>(points: I, opts: &SvgOptions) -> String where I: Iterator<Item = (f64, f64)> {
+    let mut b = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for (x, y) in points {
+        let y = flip(y);
+        b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+    }
+    let (min_x, min_y, max_x, max_y) = if b.0 > b.2 {
+        (0.0, 0.0, 0.0, 0.0)
+    } else {
+        b
+    };
+    let x = min_x - opts.margin;
+    let y = min_y - opts.margin;
+    let width = max_x - min_x + 2.0 * opts.margin;
+    let height = max_y - min_y + 2.0 * opts.margin;
+
+    let mut out = String::new();
+    // writing to a String cannot fail
+    let _ = writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+                     x, y, width, height);
+    if let Some(ref background) = opts.background {
+        let _ = writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                         x, y, width, height, escape(background));
+    }
+    let _ = writeln!(out, "<g stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\" fill=\"none\">",
+                     escape(&opts.stroke), opts.stroke_width);
+    out
+}
+
+/// Finish an element with the styling for a pen, if it needs any.
+fn pen(out: &mut String, width: f64, color: usize, opts: &SvgOptions) {
+    if let Some(color) = opts.palette.get(color) {
+        let _ = write!(out, " stroke=\"{}\"", escape(color));
+    }
+    if width != 1.0 {
+        let _ = write!(out, " stroke-width=\"{}\"", opts.stroke_width * width);
+    }
+    let _ = writeln!(out, "/>");
+}
+
+/// Close the group and document opened by `header`.
+fn footer(out: &mut String) {
+    let _ = writeln!(out, "</g>");
+    let _ = writeln!(out, "</svg>");
+}
+
+/// Escape a string for use inside a double quoted XML attribute value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Flip a y coordinate into SVG's downward-pointing y axis.  Subtracting
+/// rather than negating keeps zero from being written out as `-0`.
+fn flip(y: f64) -> f64 {
+    0.0 - y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let svg = to_svg(&[], &SvgOptions::default());
+        assert!(svg.contains("viewBox=\"-1 -1 2 2\""));
+        assert!(!svg.contains("<line"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_lines_and_background() {
+        let segments = vec![
+            Segment { start: (0.0, 0.0), end: (2.0, 1.0), width: 1.0, color: 0 },
+            Segment { start: (2.0, 1.0), end: (3.0, -1.0), width: 1.0, color: 0 },
+        ];
+        let opts = SvgOptions {
+            margin: 0.5,
+            stroke_width: 2.0,
+            stroke: "green".to_string(),
+            background: Some("white".to_string()),
+            palette: Vec::new(),
+        };
+        let svg = to_svg(&segments, &opts);
+        assert!(svg.contains("viewBox=\"-0.5 -1.5 4 3\""));
+        assert!(svg.contains("<rect x=\"-0.5\" y=\"-1.5\" width=\"4\" height=\"3\" fill=\"white\"/>"));
+        assert!(svg.contains("stroke=\"green\" stroke-width=\"2\""));
+        assert!(svg.contains("<line x1=\"0\" y1=\"0\" x2=\"2\" y2=\"-1\"/>"));
+        assert!(svg.contains("<line x1=\"2\" y1=\"-1\" x2=\"3\" y2=\"1\"/>"));
+        assert_eq!(2, svg.matches("<line").count());
+    }
+
+    #[test]
+    fn test_pen_styles() {
+        let segments = vec![
+            Segment { start: (0.0, 0.0), end: (0.0, 1.0), width: 3.0, color: 0 },
+            Segment { start: (0.0, 1.0), end: (1.0, 1.0), width: 1.0, color: 1 },
+            Segment { start: (1.0, 1.0), end: (1.0, 0.0), width: 1.0, color: 2 },
+        ];
+        let opts = SvgOptions {
+            stroke_width: 0.5,
+            palette: vec!["saddlebrown".to_string(), "green".to_string()],
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&segments, &opts);
+        assert!(svg.contains("x2=\"0\" y2=\"-1\" stroke=\"saddlebrown\" stroke-width=\"1.5\"/>"));
+        assert!(svg.contains("x2=\"1\" y2=\"-1\" stroke=\"green\"/>"));
+        assert!(svg.contains("x2=\"1\" y2=\"0\"/>"));
+    }
+
+    #[test]
+    fn test_escaped_colors() {
+        let segments = vec![Segment { start: (0.0, 0.0), end: (0.0, 1.0), width: 1.0, color: 0 }];
+        let opts = SvgOptions {
+            stroke: "a\"b".to_string(),
+            background: Some("<&>".to_string()),
+            palette: vec!["x\" onload=\"y".to_string()],
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&segments, &opts);
+        assert!(svg.contains("fill=\"&lt;&amp;&gt;\""));
+        assert!(svg.contains("<g stroke=\"a&quot;b\""));
+        assert!(svg.contains("stroke=\"x&quot; onload=&quot;y\"/>"));
+        assert!(!svg.contains("onload=\""));
+    }
+
+    #[test]
+    fn test_polylines() {
+        let polylines = vec![
+            Polyline { points: vec![(0.0, 0.0), (0.0, 1.0), (1.0, 2.0)], width: 1.0, color: 0 },
+            Polyline { points: vec![(0.0, 1.0), (-1.0, 1.0)], width: 2.0, color: 0 },
+        ];
+        let svg = polylines_to_svg(&polylines, &SvgOptions::default());
+        assert!(svg.contains("viewBox=\"-2 -3 4 4\""));
+        assert!(svg.contains("<polyline points=\"0,0 0,-1 1,-2\"/>"));
+        assert!(svg.contains("<polyline points=\"0,-1 -1,-1\" stroke-width=\"0.2\"/>"));
+        assert!(!svg.contains("<line"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Validation of L-systems against a declared vocabulary.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+use {LRules, LSystem, LSystemError, MapRules};
+
+/// The set of symbols an L-system is allowed to use.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::{LSystem, MapRules, Vocabulary};
+///
+/// let vocab: Vocabulary<char> = "AB".chars().collect();
+///
+/// let mut rules = MapRules::new();
+/// rules.set_str('A', "AB");
+/// rules.set_str('B', "AX");
+/// let system = LSystem::new(rules, vec!['A']);
+///
+/// assert_eq!(Err(vec!['X']), system.validate(&vocab));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Vocabulary<T: Hash + Eq> {
+    symbols: HashSet<T>,
+}
+
+impl<T> Vocabulary<T> where T: Hash + Eq {
+    /// Create a new, empty vocabulary.
+    pub fn new() -> Vocabulary<T> {
+        Vocabulary {
+            symbols: HashSet::new(),
+        }
+    }
+
+    /// Add a symbol to the vocabulary, returning whether it was new.
+    pub fn insert(&mut self, symbol: T) -> bool {
+        self.symbols.insert(symbol)
+    }
+
+    /// Check whether a symbol is in the vocabulary.
+    pub fn contains(&self, symbol: &T) -> bool {
+        self.symbols.contains(symbol)
+    }
+}
+
+impl<T> FromIterator<T> for Vocabulary<T> where T: Hash + Eq {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Vocabulary<T> {
+        Vocabulary {
+            symbols: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T, P> LSystem<T, P> where P: LRules<T>, T: Clone + Hash + Eq {
+    /// check that the axiom and every production the rules can make only
+    /// use symbols from `vocab`.  On failure, returns each offending symbol
+    /// once, in the order they were found.
+    ///
+    /// The rules are explored by mapping every symbol in the vocabulary and
+    /// every symbol reachable from the axiom, so a production is only checked
+    /// if it could actually be used.  Rules that choose between productions
+    /// at random are only checked for the choices made while exploring.
+    pub fn validate(&self, vocab: &Vocabulary<T>) -> Result<(), Vec<T>> {
+        let mut invalid = Vec::new();
+        let mut reported = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending: Vec<T> = self.axiom.iter().cloned()
+            .chain(vocab.symbols.iter().cloned())
+            .collect();
+        while let Some(symbol) = pending.pop() {
+            if !visited.insert(symbol.clone()) {
+                continue;
+            }
+            if !vocab.contains(&symbol) && reported.insert(symbol.clone()) {
+                invalid.push(symbol.clone());
+            }
+            if let Some(production) = self.rules.map(&symbol) {
+                pending.extend(production.into_iter().rev());
+            }
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+}
+
+impl<T> LSystem<T, MapRules<T>> where T: Clone + Hash + Eq {
+    /// every symbol the L-System mentions: those in the axiom, those with a
+    /// rule, and those any rule produces.  This includes terminals that only
+    /// appear in productions, and symbols whose rules can never be reached
+    /// from the axiom.
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use lsystem::char_system;
+    ///
+    /// let system = char_system("X", &[('X', "F[+X]"), ('F', "FF")]);
+    /// let expected: HashSet<char> = "XF[+]".chars().collect();
+    /// assert_eq!(expected, system.alphabet());
+    /// ```
+    pub fn alphabet(&self) -> HashSet<T> {
+        let mut symbols: HashSet<T> = self.axiom.iter().cloned().collect();
+        for (k, v) in self.rules.iter() {
+            symbols.insert(k.clone());
+            symbols.extend(v.iter().cloned());
+        }
+        symbols
+    }
+}
+
+impl<T> MapRules<T> where T: Clone + Hash + Eq {
+    /// Set an atom to produce a vector, like `set`, but only if the atom and
+    /// every atom it produces are in `alphabet`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LSystemError::UnknownSymbols` listing each symbol outside the
+    /// alphabet once, in order, and leaves the rules unchanged.
+    pub fn set_checked(&mut self, k: T, v: Vec<T>, alphabet: &Vocabulary<T>) -> Result<Option<Vec<T>>, LSystemError<T>> {
+        let mut unknown: Vec<T> = Vec::new();
+        for symbol in Some(&k).into_iter().chain(v.iter()) {
+            if !alphabet.contains(symbol) && !unknown.contains(symbol) {
+                unknown.push(symbol.clone());
+            }
+        }
+        if unknown.is_empty() {
+            Ok(self.set(k, v))
+        } else {
+            Err(LSystemError::UnknownSymbols(unknown))
+        }
+    }
+}
+
+impl MapRules<char> {
+    /// Create a ruleset from pairs of atoms and the strings they produce,
+    /// like `from_str_rules`, rejecting any pair that uses a character
+    /// outside `alphabet`.  This catches typos in rules read from user
+    /// input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LSystemError::UnknownSymbols` for the first pair that uses
+    /// characters outside the alphabet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lsystem::{LSystemError, MapRules, Vocabulary};
+    ///
+    /// let alphabet: Vocabulary<char> = "F+-[]".chars().collect();
+    /// assert!(MapRules::from_str_rules_checked(&[('F', "F[+F]F")], &alphabet).is_ok());
+    ///
+    /// let typo = MapRules::from_str_rules_checked(&[('F', "F[+F}F")], &alphabet);
+    /// assert_eq!(Some(LSystemError::UnknownSymbols(vec!['}'])), typo.err());
+    /// ```
+    pub fn from_str_rules_checked(pairs: &[(char, &str)], alphabet: &Vocabulary<char>) -> Result<MapRules<char>, LSystemError> {
+        let mut rules = MapRules::new();
+        for &(k, v) in pairs.iter() {
+            rules.set_checked(k, v.chars().collect(), alphabet)?;
+        }
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid() {
+        let vocab: Vocabulary<char> = "01[]".chars().collect();
+        let mut rules = MapRules::new();
+        rules.set_str('1', "11");
+        rules.set_str('0', "1[0]0");
+        let system = LSystem::new(rules, vec!['0']);
+        assert_eq!(Ok(()), system.validate(&vocab));
+    }
+
+    #[test]
+    fn test_invalid_reported_once() {
+        let vocab: Vocabulary<char> = "AB".chars().collect();
+        let mut rules = MapRules::new();
+        rules.set_str('A', "AXBX");
+        rules.set_str('B', "YX");
+        let system = LSystem::new(rules, vec!['A', 'Z', 'Z']);
+        let mut invalid = system.validate(&vocab).unwrap_err();
+        invalid.sort();
+        assert_eq!(vec!['X', 'Y', 'Z'], invalid);
+    }
+
+    #[test]
+    fn test_unreachable_rule_checked() {
+        let vocab: Vocabulary<char> = "AB".chars().collect();
+        let mut rules = MapRules::new();
+        rules.set_str('B', "Q");
+        let system = LSystem::new(rules, vec!['A']);
+        assert_eq!(Err(vec!['Q']), system.validate(&vocab));
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let mut rules = MapRules::new();
+        rules.set(1, vec![1, 2]);
+        rules.set(7, vec![8]);
+        rules.set(9, vec![]);
+        let mut system = LSystem::new(rules, vec![0, 1]);
+        system.nth(3);
+        let expected: HashSet<u8> = vec![0, 1, 2, 7, 8, 9].into_iter().collect();
+        assert_eq!(expected, system.alphabet());
+    }
+
+    #[test]
+    fn test_set_checked() {
+        let alphabet: Vocabulary<u8> = vec![0, 1].into_iter().collect();
+        let mut rules = MapRules::new();
+        assert_eq!(Ok(None), rules.set_checked(0, vec![0, 1], &alphabet));
+        assert_eq!(Err(LSystemError::UnknownSymbols(vec![2, 3])),
+                   rules.set_checked(2, vec![3, 1, 3], &alphabet));
+        assert_eq!(1, rules.len());
+    }
+}
@@ -0,0 +1,45 @@
+//! A flat, string based interface for calling the crate from JavaScript.
+//!
+//! Everything here takes and returns plain strings and numbers, so it can be
+//! exported across a WebAssembly boundary without exposing the crate's own
+//! types.  On `wasm32` targets the functions are exported with
+//! `wasm-bindgen`.
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use {LSystem, MapRules};
+
+/// Parse rules written one per line as `A -> AB`, run them from `axiom` for
+/// `generations` generations, and return every generation as a string,
+/// starting with the axiom.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the rules do not parse, or if
+/// the system stops changing before reaching the requested generation.
+///
+/// # Examples
+///
+/// ```
+/// use lsystem::wasm::run_char_system;
+///
+/// let generations = run_char_system("A -> AB\nB -> A", "A", 3).unwrap();
+/// assert_eq!(vec!["A", "AB", "ABA", "ABAAB"], generations);
+///
+/// assert!(run_char_system("A AB", "A", 3).is_err());
+/// assert!(run_char_system("A -> B", "A", 3).is_err());
+/// ```
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn run_char_system(rules: &str, axiom: &str, generations: usize) -> Result<Vec<String>, String> {
+    let rules = MapRules::from_rules_str(rules).map_err(|e| e.to_string())?;
+    let mut system = LSystem::new(rules, axiom.chars().collect());
+    let mut out = vec![axiom.to_string()];
+    for _ in 0..generations {
+        if !system.advance() {
+            return Err(format!("the system stopped changing after {} generations", system.generation()));
+        }
+        out.push(system.current_state().iter().collect());
+    }
+    Ok(out)
+}